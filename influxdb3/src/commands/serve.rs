@@ -17,7 +17,7 @@ use influxdb3_server::{
 use influxdb3_write::persister::PersisterImpl;
 use influxdb3_write::wal::WalImpl;
 use influxdb3_write::write_buffer::WriteBufferImpl;
-use influxdb3_write::SegmentDuration;
+use influxdb3_write::{ParquetLayout, ReplayMode, SegmentDuration};
 use iox_query::exec::{DedicatedExecutor, Executor, ExecutorConfig};
 use iox_time::SystemProvider;
 use ioxd_common::reexport::trace_http::ctx::TraceHeaderParser;
@@ -63,6 +63,9 @@ pub enum Error {
     #[error("Write buffer error: {0}")]
     WriteBuffer(#[from] influxdb3_write::write_buffer::Error),
 
+    #[error("Persister error: {0}")]
+    Persister(#[from] influxdb3_write::persister::Error),
+
     #[error("invalid token: {0}")]
     InvalidToken(#[from] hex::FromHexError),
 }
@@ -167,6 +170,39 @@ pub struct Config {
         action
     )]
     pub query_log_size: usize,
+
+    /// Reject writes while more than this many closed segments are waiting to be persisted to
+    /// object storage, until persistence catches up. If not specified, writes are never
+    /// rejected because of a persistence backlog.
+    #[clap(
+        long = "max-persisting-segments",
+        env = "INFLUXDB3_MAX_PERSISTING_SEGMENTS",
+        action
+    )]
+    pub max_persisting_segments: Option<usize>,
+
+    /// Which WAL segments that weren't persisted before the server stopped are replayed on
+    /// startup for every database, overriding each database's own replay mode: `full` replays
+    /// all of them, `skip` replays none, and a duration such as `30m` replays only segments
+    /// covering that much time before startup. Segments that aren't replayed are deleted and
+    /// their writes are lost. If unset, databases are replayed in full unless configured
+    /// otherwise.
+    #[clap(long = "wal-replay", env = "INFLUXDB3_WAL_REPLAY", action)]
+    pub wal_replay: Option<ReplayMode>,
+
+    /// How the paths of persisted parquet files are laid out in object storage, under
+    /// `dbs/<db>/`: `segment` uses a directory per table and segment, and `hive` a
+    /// `<table>/date=YYYY-MM-DD` directory per table and day that engines such as Trino or Athena
+    /// can read as a partitioned table. Anything else is a template of the directories with
+    /// `{db}`, `{table}`, and `{date}` placeholders, such as `{table}/date={date}`, which is what
+    /// `hive` stands for. Only affects newly persisted files.
+    #[clap(
+        long = "parquet-layout",
+        env = "INFLUXDB3_PARQUET_LAYOUT",
+        default_value = "segment",
+        action
+    )]
+    pub parquet_layout: ParquetLayout,
 }
 
 /// If `p` does not exist, try to create it as a directory.
@@ -262,23 +298,34 @@ pub async fn command(config: Config) -> Result<()> {
         trace_header_parser,
         *config.http_bind_address,
     )?;
-    let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+    let persister = Arc::new(
+        PersisterImpl::new(Arc::clone(&object_store))
+            .with_metrics(&metrics)
+            .with_parquet_layout(config.parquet_layout),
+    );
+    // take over persistence from any server still running against this object store, such as
+    // one that was failed over from
+    let fencing_epoch = persister.acquire_fence().await?;
+    info!(fencing_epoch, "acquired fencing epoch");
     let wal: Option<Arc<WalImpl>> = config
         .wal_directory
         .map(|dir| WalImpl::new(dir).map(Arc::new))
         .transpose()?;
 
     let time_provider = Arc::new(SystemProvider::new());
-    let write_buffer = Arc::new(
-        WriteBufferImpl::new(
-            Arc::clone(&persister),
-            wal,
-            Arc::clone(&time_provider),
-            config.segment_duration,
-            Arc::clone(&exec),
-        )
-        .await?,
-    );
+    let mut write_buffer = WriteBufferImpl::new(
+        Arc::clone(&persister),
+        wal,
+        Arc::clone(&time_provider),
+        config.segment_duration,
+        Arc::clone(&exec),
+        config.wal_replay,
+    )
+    .await?;
+    if let Some(limit) = config.max_persisting_segments {
+        write_buffer = write_buffer.with_max_persisting_segments(limit, &metrics);
+    }
+    let write_buffer = Arc::new(write_buffer);
     let query_executor = Arc::new(QueryExecutorImpl::new(
         write_buffer.catalog(),
         Arc::clone(&write_buffer),
@@ -108,17 +108,20 @@ async fn flight() -> Result<(), influxdb3_client::Error> {
 
         assert_batches_sorted_eq!(
             [
-                "+--------------+--------------------+-------------+------------+",
-                "| catalog_name | db_schema_name     | table_name  | table_type |",
-                "+--------------+--------------------+-------------+------------+",
-                "| public       | information_schema | columns     | VIEW       |",
-                "| public       | information_schema | df_settings | VIEW       |",
-                "| public       | information_schema | schemata    | VIEW       |",
-                "| public       | information_schema | tables      | VIEW       |",
-                "| public       | information_schema | views       | VIEW       |",
-                "| public       | iox                | cpu         | BASE TABLE |",
-                "| public       | system             | queries     | BASE TABLE |",
-                "+--------------+--------------------+-------------+------------+",
+                "+--------------+--------------------+----------------------+------------+",
+                "| catalog_name | db_schema_name     | table_name           | table_type |",
+                "+--------------+--------------------+----------------------+------------+",
+                "| public       | information_schema | columns              | VIEW       |",
+                "| public       | information_schema | df_settings          | VIEW       |",
+                "| public       | information_schema | schemata             | VIEW       |",
+                "| public       | information_schema | tables               | VIEW       |",
+                "| public       | information_schema | views                | VIEW       |",
+                "| public       | iox                | cpu                  | BASE TABLE |",
+                "| public       | system             | parquet_file_columns | BASE TABLE |",
+                "| public       | system             | parquet_files        | BASE TABLE |",
+                "| public       | system             | queries              | BASE TABLE |",
+                "| public       | system             | schema_changes       | BASE TABLE |",
+                "+--------------+--------------------+----------------------+------------+",
             ],
             &batches
         );
@@ -140,6 +143,28 @@ async fn flight() -> Result<(), influxdb3_client::Error> {
         );
     }
 
+    // Get DB Schemas
+    {
+        type OptStr = std::option::Option<&'static str>;
+        let stream = client
+            .get_db_schemas(OptStr::None, OptStr::None)
+            .await
+            .unwrap();
+        let batches = collect_stream(stream).await;
+        assert_batches_sorted_eq!(
+            [
+                "+--------------+--------------------+",
+                "| catalog_name | db_schema_name     |",
+                "+--------------+--------------------+",
+                "| public       | information_schema |",
+                "| public       | iox                |",
+                "| public       | system             |",
+                "+--------------+--------------------+",
+            ],
+            &batches
+        );
+    }
+
     Ok(())
 }
 
@@ -227,3 +252,49 @@ async fn flight_influxql() {
         );
     }
 }
+
+#[tokio::test]
+async fn flight_sql_params() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1,region=us-east usage=0.9 1\n\
+            cpu,host=s2,region=us-east usage=0.89 2\n\
+            cpu,host=s1,region=us-east usage=0.85 3",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    let mut client = server.flight_client().await;
+
+    // Parameters are bound by the planner rather than interpolated into the query:
+    {
+        let ticket = Ticket::new(
+            r#"{
+                    "database": "foo",
+                    "sql_query": "SELECT host, time, usage FROM cpu WHERE host = $host AND usage > $min",
+                    "query_type": "sql",
+                    "params": {
+                        "host": "s1",
+                        "min": 0.86
+                    }
+                }"#,
+        );
+        let response = client.do_get(ticket).await.unwrap();
+
+        let batches = collect_stream(response).await;
+        assert_batches_sorted_eq!(
+            [
+                "+------+--------------------------------+-------+",
+                "| host | time                           | usage |",
+                "+------+--------------------------------+-------+",
+                "| s1   | 1970-01-01T00:00:00.000000001Z | 0.9   |",
+                "+------+--------------------------------+-------+",
+            ],
+            &batches
+        );
+    }
+}
@@ -914,3 +914,119 @@ async fn api_v1_query_chunked() {
         assert_eq!(t.expected, values, "query failed: {q}", q = t.query);
     }
 }
+
+#[tokio::test]
+async fn api_v3_query_timeout() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1,region=us-east usage=0.9 1",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let configure_url = format!(
+        "{base}/api/v3/configure/query_timeout",
+        base = server.client_addr()
+    );
+
+    // a zero timeout would fail every query
+    let resp = client
+        .post(&configure_url)
+        .body(json!({"db": "foo", "timeout_ms": 0}).to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let resp = client
+        .post(&configure_url)
+        .body(json!({"db": "foo", "timeout_ms": 60_000}).to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+    // queries that finish within their timeout aren't marked as truncated
+    let resp = client
+        .get(format!(
+            "{base}/api/v3/query_sql",
+            base = server.client_addr()
+        ))
+        .query(&[
+            ("db", "foo"),
+            ("q", "SELECT host, usage FROM cpu"),
+            ("format", "pretty"),
+            ("timeout_ms", "30000"),
+            ("accept_partial", "true"),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    assert!(resp.headers().get("x-influxdb-query-truncated").is_none());
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | s1   | 0.9   |\n\
+        +------+-------+",
+        resp.text().await.unwrap(),
+    );
+}
+
+#[tokio::test]
+async fn api_v3_query_retention_period() {
+    let server = TestServer::spawn().await;
+
+    // one point from long ago and one timestamped by the server when it is written
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1 usage=0.9 1\ncpu,host=s2 usage=0.5",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!(
+            "{base}/api/v3/configure/retention",
+            base = server.client_addr()
+        ))
+        .body(json!({"db": "foo", "retention_period_ms": 86_400_000}).to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+    let resp = client
+        .get(format!(
+            "{base}/api/v3/query_sql",
+            base = server.client_addr()
+        ))
+        .query(&[
+            ("db", "foo"),
+            ("q", "SELECT host, usage FROM cpu"),
+            ("format", "pretty"),
+        ])
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | s2   | 0.5   |\n\
+        +------+-------+",
+        resp,
+    );
+}
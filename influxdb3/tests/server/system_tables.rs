@@ -90,3 +90,45 @@ async fn queries_table() {
         );
     }
 }
+
+#[tokio::test]
+async fn schema_changes_table() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=s1 usage=0.9 1", Precision::Nanosecond)
+        .await
+        .expect("write some lp");
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1,region=us-east usage=0.89 2",
+            Precision::Nanosecond,
+        )
+        .await
+        .expect("write some lp");
+
+    let mut client = server.flight_sql_client("foo").await;
+    let response = client
+        .query(
+            "SELECT table_name, column_name, column_type, origin \
+            FROM system.schema_changes",
+        )
+        .await
+        .unwrap();
+
+    let batches = collect_stream(response).await;
+    assert_batches_sorted_eq!(
+        [
+            "+------------+-------------+-------------+--------+",
+            "| table_name | column_name | column_type | origin |",
+            "+------------+-------------+-------------+--------+",
+            "| cpu        | host        | tag         | write  |",
+            "| cpu        | region      | tag         | write  |",
+            "| cpu        | time        | time        | write  |",
+            "| cpu        | usage       | f64         | write  |",
+            "+------------+-------------+-------------+--------+",
+        ],
+        &batches
+    );
+}
@@ -320,3 +320,290 @@ async fn writes_with_different_schema_should_fail() {
         "the request should hae failed with an API Error"
     );
 }
+
+#[tokio::test]
+async fn api_v3_configure_field_validation() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let configure_url = format!(
+        "{base}/api/v3/configure/field_validation",
+        base = server.client_addr()
+    );
+
+    // the field must exist before a rule can be set on it
+    let body = serde_json::json!({
+        "db": "foo",
+        "table": "cpu",
+        "field": "usage",
+        "rule": {"type": "range", "min": 0.0, "max": 1.0},
+        "action": "clamp",
+    });
+    let resp = client
+        .post(&configure_url)
+        .body(body.to_string())
+        .send()
+        .await
+        .expect("send configure request");
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.5 1", Precision::Nanosecond)
+        .await
+        .expect("write lp");
+
+    let resp = client
+        .post(&configure_url)
+        .body(body.to_string())
+        .send()
+        .await
+        .expect("send configure request");
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=1.7 2", Precision::Nanosecond)
+        .await
+        .expect("write lp");
+
+    let resp = server
+        .api_v3_query_influxql(&[
+            ("q", "SELECT time, host, usage FROM foo.autogen.cpu"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        "+------------------+-------------------------------+------+-------+\n\
+        | iox::measurement | time                          | host | usage |\n\
+        +------------------+-------------------------------+------+-------+\n\
+        | cpu              | 1970-01-01T00:00:00.000000001 | a    | 0.5   |\n\
+        | cpu              | 1970-01-01T00:00:00.000000002 | a    | 1.0   |\n\
+        +------------------+-------------------------------+------+-------+"
+    );
+}
+
+#[tokio::test]
+async fn api_v3_configure_time_validation() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let configure_url = format!(
+        "{base}/api/v3/configure/time_validation",
+        base = server.client_addr()
+    );
+
+    // auto isn't a precision that can be required
+    let resp = client
+        .post(&configure_url)
+        .body(serde_json::json!({"db": "foo", "precision": "auto"}).to_string())
+        .send()
+        .await
+        .expect("send configure request");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = client
+        .post(&configure_url)
+        .body(serde_json::json!({"db": "foo", "min_time": 10}).to_string())
+        .send()
+        .await
+        .expect("send configure request");
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let err = server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=0.5 5\ncpu,host=a usage=0.7 20",
+            Precision::Nanosecond,
+        )
+        .await
+        .expect_err("write with a point before the minimum time");
+    assert!(err
+        .to_string()
+        .contains("before the earliest accepted time"));
+
+    let resp = server
+        .api_v3_query_influxql(&[
+            ("q", "SELECT time, host, usage FROM foo.autogen.cpu"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        "+------------------+-------------------------------+------+-------+\n\
+        | iox::measurement | time                          | host | usage |\n\
+        +------------------+-------------------------------+------+-------+\n\
+        | cpu              | 1970-01-01T00:00:00.000000020 | a    | 0.7   |\n\
+        +------------------+-------------------------------+------+-------+"
+    );
+}
+
+#[tokio::test]
+async fn api_v3_configure_strict_tables() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let base = server.client_addr();
+
+    let resp = client
+        .post(format!("{base}/api/v3/configure/table"))
+        .body(serde_json::json!({"db": "foo", "table": "cpu", "tags": ["host"]}).to_string())
+        .send()
+        .await
+        .expect("send create table request");
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = client
+        .post(format!("{base}/api/v3/configure/strict_tables"))
+        .body(serde_json::json!({"db": "foo", "enabled": true}).to_string())
+        .send()
+        .await
+        .expect("send configure request");
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let err = server
+        .write_lp_to_db(
+            "foo",
+            "cpy,host=a usage=0.5 1\ncpu,host=a usage=0.7 2",
+            Precision::Nanosecond,
+        )
+        .await
+        .expect_err("write to an undeclared table");
+    assert!(err.to_string().contains("does not exist"));
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.7 2", Precision::Nanosecond)
+        .await
+        .expect("write to a declared table");
+
+    let resp = server
+        .api_v3_query_influxql(&[("q", "SHOW MEASUREMENTS ON foo"), ("format", "pretty")])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        "+------------------+------+\n\
+        | iox::measurement | name |\n\
+        +------------------+------+\n\
+        | measurements     | cpu  |\n\
+        +------------------+------+"
+    );
+}
+
+#[tokio::test]
+async fn api_v3_configure_load_shedding() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let base = server.client_addr();
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.5 1", Precision::Nanosecond)
+        .await
+        .expect("write before shedding load");
+
+    let resp = client
+        .post(format!("{base}/api/v3/configure/load_shedding"))
+        .body(serde_json::json!({"db": "foo", "shed_percent": 101}).to_string())
+        .send()
+        .await
+        .expect("send configure request");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = client
+        .post(format!("{base}/api/v3/configure/load_shedding"))
+        .body(serde_json::json!({"db": "foo", "shed_percent": 100}).to_string())
+        .send()
+        .await
+        .expect("send configure request");
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = client
+        .post(format!("{base}/api/v3/write_lp"))
+        .query(&[("db", "foo")])
+        .body("cpu,host=a usage=0.6 2\ncpu,host=a usage=0.7 3")
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["x-influxdb-shed-lines"], "2");
+
+    let resp = server
+        .api_v3_query_influxql(&[
+            ("q", "SELECT time, host, usage FROM foo.autogen.cpu"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        "+------------------+-------------------------------+------+-------+\n\
+        | iox::measurement | time                          | host | usage |\n\
+        +------------------+-------------------------------+------+-------+\n\
+        | cpu              | 1970-01-01T00:00:00.000000001 | a    | 0.5   |\n\
+        +------------------+-------------------------------+------+-------+"
+    );
+}
+
+#[tokio::test]
+async fn api_v3_configure_quarantine_rejected_writes() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let base = server.client_addr();
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.5 1", Precision::Nanosecond)
+        .await
+        .expect("write before quarantining rejected writes");
+
+    let resp = client
+        .post(format!(
+            "{base}/api/v3/configure/quarantine_rejected_writes"
+        ))
+        .body(serde_json::json!({"db": "foo", "enabled": true}).to_string())
+        .send()
+        .await
+        .expect("send configure request");
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = client
+        .post(format!("{base}/api/v3/write_lp"))
+        .query(&[("db", "foo")])
+        .body("cpu,host=a usage=0.6 2\ncpu,host=a usage=\"high\" 3")
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = client
+        .get(format!("{base}/api/v3/query_sql"))
+        .query(&[
+            ("db", "foo"),
+            ("q", "SELECT line_number, line FROM _rejected_writes"),
+            ("format", "pretty"),
+        ])
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        "+-------------+-----------------------------+\n\
+        | line_number | line                        |\n\
+        +-------------+-----------------------------+\n\
+        | 2           | cpu,host=a usage=\"high\" 3 |\n\
+        +-------------+-----------------------------+"
+    );
+}
@@ -10,7 +10,6 @@ use bytes::{Bytes, BytesMut};
 use data_types::NamespaceName;
 use datafusion::error::DataFusionError;
 use datafusion::execution::memory_pool::UnboundedMemoryPool;
-use datafusion::execution::RecordBatchStream;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use futures::{StreamExt, TryStreamExt};
 use hyper::header::ACCEPT;
@@ -22,10 +21,14 @@ use hyper::HeaderMap;
 use hyper::{Body, Method, Request, Response, StatusCode};
 use influxdb3_process::{INFLUXDB3_GIT_HASH_SHORT, INFLUXDB3_VERSION};
 use influxdb3_write::catalog::Error as CatalogError;
+use influxdb3_write::catalog::{
+    FieldRule, FieldValidation, TagNormalization, TimeValidation, ValidationAction,
+};
 use influxdb3_write::persister::TrackedMemoryArrowWriter;
 use influxdb3_write::write_buffer::Error as WriteBufferError;
 use influxdb3_write::BufferedWriteRequest;
 use influxdb3_write::Precision;
+use influxdb3_write::ReplayMode;
 use influxdb3_write::WriteBuffer;
 use iox_http::write::single_tenant::SingleTenantRequestUnifier;
 use iox_http::write::v1::V1_NAMESPACE_RP_SEPARATOR;
@@ -33,17 +36,21 @@ use iox_http::write::{WriteParseError, WriteRequestUnifier};
 use iox_query_influxql_rewrite as rewrite;
 use iox_query_params::StatementParams;
 use iox_time::TimeProvider;
+use metric::{Metric, U64Counter};
 use observability_deps::tracing::{debug, error, info};
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 use std::convert::Infallible;
 use std::fmt::Debug;
-use std::pin::Pin;
+use std::future::Future;
+use std::num::{NonZeroU64, NonZeroU8};
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::time::Instant;
 use unicode_segmentation::UnicodeSegmentation;
 
 mod v1;
@@ -99,6 +106,13 @@ pub enum Error {
     #[error("access denied")]
     Forbidden,
 
+    /// The `Authorization` header isn't a bearer token.
+    #[error(
+        "Authorization header was malformed and should be in the form \
+        'Authorization: Bearer <token>'"
+    )]
+    MalformedAuthorization,
+
     /// The HTTP request method is not supported for this resource
     #[error("unsupported method")]
     UnsupportedMethod,
@@ -184,6 +198,13 @@ pub enum Error {
 
     #[error("v1 query API error: {0}")]
     V1Query(#[from] v1::QueryError),
+
+    #[error("catalog error: {0}")]
+    Catalog(#[from] CatalogError),
+
+    /// The query ran past its timeout and was cancelled
+    #[error("query exceeded its timeout of {0:?} and was cancelled")]
+    QueryTimeout(Duration),
 }
 
 #[derive(Debug, Error)]
@@ -198,86 +219,224 @@ pub enum AuthorizationError {
     ToStr(#[from] hyper::header::ToStrError),
 }
 
+/// Stable identifiers for the kinds of error returned in HTTP error bodies. Clients should match
+/// on these rather than on the error message, which may change between releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorCode {
+    /// The line protocol in a write could not be parsed or didn't match the schema
+    InvalidLineProtocol,
+    /// A write conflicts with the type of an existing column
+    SchemaMismatch,
+    /// Some lines of a write were rejected, the rest were accepted
+    PartialWrite,
+    /// The database name is not valid
+    InvalidDatabaseName,
+    /// The request was malformed or had invalid parameters
+    InvalidRequest,
+    /// The write would exceed a limit on the number of databases, tables or columns
+    LimitExceeded,
+    /// A database, table or column named in the request doesn't exist
+    NotFound,
+    /// A configuration change was rejected as invalid
+    InvalidConfiguration,
+    /// The query could not be planned or executed
+    QueryFailed,
+    /// The query ran longer than its timeout and was cancelled
+    QueryTimeout,
+    /// The HTTP method is not supported for the resource
+    UnsupportedMethod,
+    /// The server is overloaded
+    Overloaded,
+    /// The request wasn't authenticated or isn't allowed to access the resource
+    Unauthorized,
+    /// Data couldn't be persisted to object storage, or this server is no longer allowed to
+    /// persist it
+    PersistenceFailed,
+    /// An internal error
+    Internal,
+}
+
+impl ErrorCode {
+    /// Whether the same request may succeed if retried later
+    fn is_retryable(self) -> bool {
+        matches!(self, Self::Overloaded | Self::Internal)
+    }
+
+    /// The SQLSTATE, as used by PostgreSQL, closest to this code, so that SQL clients can handle
+    /// errors the way they would for other databases
+    fn sqlstate(self) -> &'static str {
+        match self {
+            Self::InvalidLineProtocol | Self::PartialWrite => "22000",
+            Self::SchemaMismatch => "42804",
+            Self::InvalidDatabaseName => "3D000",
+            Self::InvalidRequest => "08P01",
+            Self::LimitExceeded => "54000",
+            Self::NotFound => "42704",
+            Self::InvalidConfiguration => "22023",
+            Self::QueryFailed => "42000",
+            Self::QueryTimeout => "57014",
+            Self::UnsupportedMethod => "0A000",
+            Self::Overloaded => "53000",
+            Self::Unauthorized => "28000",
+            Self::PersistenceFailed => "58030",
+            Self::Internal => "XX000",
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct ErrorMessage<T: Serialize> {
     error: String,
+    code: ErrorCode,
+    sqlstate: &'static str,
+    retryable: bool,
     data: Option<T>,
 }
 
+impl<T: Serialize> ErrorMessage<T> {
+    fn new(code: ErrorCode, error: impl Into<String>, data: Option<T>) -> Self {
+        Self {
+            error: error.into(),
+            code,
+            sqlstate: code.sqlstate(),
+            retryable: code.is_retryable(),
+            data,
+        }
+    }
+
+    /// Convert this message into an HTTP [`Response`] with the given status
+    fn into_response(self, status: StatusCode) -> Response<Body> {
+        let serialized = serde_json::to_string(&self).unwrap();
+        Response::builder()
+            .status(status)
+            .body(Body::from(serialized))
+            .unwrap()
+    }
+}
+
+impl From<&CatalogError> for ErrorCode {
+    fn from(err: &CatalogError) -> Self {
+        match err {
+            CatalogError::TooManyDbs
+            | CatalogError::TooManyColumns
+            | CatalogError::TooManyTables => Self::LimitExceeded,
+            CatalogError::DatabaseNotFound(_)
+            | CatalogError::TableNotFound { .. }
+            | CatalogError::FieldNotFound { .. } => Self::NotFound,
+            CatalogError::InvalidFieldValidation { .. }
+            | CatalogError::InvalidTimeValidation { .. }
+            | CatalogError::InvalidTagNormalization { .. }
+            | CatalogError::InvalidTableDefinition { .. }
+            | CatalogError::InvalidLoadShedding { .. } => Self::InvalidConfiguration,
+            CatalogError::CatalogUpdatedElsewhere => Self::Internal,
+        }
+    }
+}
+
 impl Error {
+    /// The [`ErrorCode`] reported to clients for this error
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Catalog(err) | Self::WriteBuffer(WriteBufferError::CatalogUpdateError(err)) => {
+                ErrorCode::from(err)
+            }
+            Self::WriteBuffer(WriteBufferError::ParseError(_)) | Self::ParseLineProtocol(_) => {
+                ErrorCode::InvalidLineProtocol
+            }
+            Self::WriteBuffer(WriteBufferError::ColumnTypeMismatch { .. }) => {
+                ErrorCode::SchemaMismatch
+            }
+            Self::PartialLpWrite(_) => ErrorCode::PartialWrite,
+            Self::DbName(_)
+            | Self::InvalidNamespaceName(_)
+            | Self::WriteBuffer(WriteBufferError::DatabaseNameError(_)) => {
+                ErrorCode::InvalidDatabaseName
+            }
+            Self::NoHandler
+            | Self::NonUtf8Body(_)
+            | Self::NonUtf8ContentHeader(_)
+            | Self::InvalidContentEncoding(_)
+            | Self::RequestSizeExceeded(_)
+            | Self::InvalidGzip(_)
+            | Self::MalformedAuthorization
+            | Self::MissingQueryParams
+            | Self::MissingWriteParams
+            | Self::Serde(_)
+            | Self::SerdeJson(_)
+            | Self::QueryParams(_)
+            | Self::InfluxqlSingleStatement
+            | Self::InfluxqlNoDatabase
+            | Self::InfluxqlDatabaseMismatch { .. } => ErrorCode::InvalidRequest,
+            Self::Query(_) | Self::Datafusion(_) | Self::InfluxqlRewrite(_) | Self::V1Query(_) => {
+                ErrorCode::QueryFailed
+            }
+            Self::QueryTimeout(_) => ErrorCode::QueryTimeout,
+            Self::UnsupportedMethod => ErrorCode::UnsupportedMethod,
+            Self::RequestLimit | Self::WriteBuffer(WriteBufferError::PersistBacklog { .. }) => {
+                ErrorCode::Overloaded
+            }
+            Self::Unauthenticated | Self::Forbidden => ErrorCode::Unauthorized,
+            Self::Persister(_) | Self::WriteBuffer(WriteBufferError::PersisterError(_)) => {
+                ErrorCode::PersistenceFailed
+            }
+            Self::WriteBuffer(
+                WriteBufferError::WalError(_)
+                | WriteBufferError::BufferSegmentError(_)
+                | WriteBufferError::CorruptLoadState(_)
+                | WriteBufferError::WalOpForMultipleSegments(_)
+                | WriteBufferError::TableBufferError(_),
+            )
+            | Self::ClientHangup(_)
+            | Self::ServingHttp(_)
+            | Self::Arrow(_)
+            | Self::Hyper(_)
+            | Self::ToStr(_)
+            | Self::Influxdb3Write(_)
+            | Self::Io(_) => ErrorCode::Internal,
+        }
+    }
+
     /// Convert this error into an HTTP [`Response`]
     fn into_response(self) -> Response<Body> {
+        let code = self.code();
         match self {
-            Self::WriteBuffer(WriteBufferError::CatalogUpdateError(
-                err @ (CatalogError::TooManyDbs
-                | CatalogError::TooManyColumns
-                | CatalogError::TooManyTables),
-            )) => {
-                let err: ErrorMessage<()> = ErrorMessage {
-                    error: err.to_string(),
-                    data: None,
-                };
-                let serialized = serde_json::to_string(&err).unwrap();
-                let body = Body::from(serialized);
-                Response::builder()
-                    .status(StatusCode::UNPROCESSABLE_ENTITY)
-                    .body(body)
-                    .unwrap()
-            }
             Self::WriteBuffer(WriteBufferError::ParseError(err)) => {
-                let err = ErrorMessage {
-                    error: "parsing failed for write_lp endpoint".into(),
-                    data: Some(err),
-                };
-                let serialized = serde_json::to_string(&err).unwrap();
-                let body = Body::from(serialized);
-                Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(body)
-                    .unwrap()
-            }
-            Self::DbName(e) => {
-                let err: ErrorMessage<()> = ErrorMessage {
-                    error: e.to_string(),
-                    data: None,
-                };
-                let serialized = serde_json::to_string(&err).unwrap();
-                let body = Body::from(serialized);
-                Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(body)
-                    .unwrap()
+                ErrorMessage::new(code, "parsing failed for write_lp endpoint", Some(err))
+                    .into_response(StatusCode::BAD_REQUEST)
             }
-            Self::PartialLpWrite(data) => {
-                let err = ErrorMessage {
-                    error: "partial write of line protocol occurred".into(),
-                    data: Some(data.invalid_lines),
+            Self::PartialLpWrite(data) => ErrorMessage::new(
+                code,
+                "partial write of line protocol occurred",
+                Some(data.invalid_lines),
+            )
+            .into_response(StatusCode::BAD_REQUEST),
+            Self::Catalog(err) | Self::WriteBuffer(WriteBufferError::CatalogUpdateError(err)) => {
+                let status = match code {
+                    ErrorCode::LimitExceeded => StatusCode::UNPROCESSABLE_ENTITY,
+                    ErrorCode::NotFound => StatusCode::NOT_FOUND,
+                    ErrorCode::InvalidConfiguration => StatusCode::BAD_REQUEST,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
                 };
-                let serialized = serde_json::to_string(&err).unwrap();
-                let body = Body::from(serialized);
-                Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(body)
-                    .unwrap()
+                ErrorMessage::<()>::new(code, err.to_string(), None).into_response(status)
             }
-            Self::UnsupportedMethod => {
-                let err: ErrorMessage<()> = ErrorMessage {
-                    error: self.to_string(),
-                    data: None,
+            err => {
+                let status = match err {
+                    Self::DbName(_)
+                    | Self::MalformedAuthorization
+                    | Self::WriteBuffer(WriteBufferError::ColumnTypeMismatch { .. }) => {
+                        StatusCode::BAD_REQUEST
+                    }
+                    Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+                    Self::Forbidden => StatusCode::FORBIDDEN,
+                    Self::WriteBuffer(WriteBufferError::PersistBacklog { .. }) => {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    }
+                    Self::QueryTimeout(_) => StatusCode::REQUEST_TIMEOUT,
+                    Self::UnsupportedMethod => StatusCode::METHOD_NOT_ALLOWED,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
                 };
-                let serialized = serde_json::to_string(&err).unwrap();
-                let body = Body::from(serialized);
-                Response::builder()
-                    .status(StatusCode::METHOD_NOT_ALLOWED)
-                    .body(body)
-                    .unwrap()
-            }
-            _ => {
-                let body = Body::from(self.to_string());
-                Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(body)
-                    .unwrap()
+                ErrorMessage::<()>::new(code, err.to_string(), None).into_response(status)
             }
         }
     }
@@ -294,6 +453,7 @@ pub(crate) struct HttpApi<W, Q, T> {
     max_request_bytes: usize,
     authorizer: Arc<dyn Authorizer>,
     legacy_write_param_unifier: SingleTenantRequestUnifier,
+    shed_lines: Metric<U64Counter>,
 }
 
 impl<W, Q, T> HttpApi<W, Q, T> {
@@ -306,6 +466,10 @@ impl<W, Q, T> HttpApi<W, Q, T> {
         authorizer: Arc<dyn Authorizer>,
     ) -> Self {
         let legacy_write_param_unifier = SingleTenantRequestUnifier::new(Arc::clone(&authorizer));
+        let shed_lines = common_state.metrics.register_metric::<U64Counter>(
+            "influxdb3_write_lines_shed",
+            "Number of written lines dropped because their database is shedding load",
+        );
         Self {
             common_state,
             time_provider,
@@ -314,6 +478,7 @@ impl<W, Q, T> HttpApi<W, Q, T> {
             max_request_bytes,
             authorizer,
             legacy_write_param_unifier,
+            shed_lines,
         }
     }
 }
@@ -340,6 +505,7 @@ where
         validate_db_name(&params.db, accept_rp)?;
         info!("write_lp to {}", params.db);
 
+        let source = write_source(&req);
         let body = self.read_body(req).await?;
         let body = std::str::from_utf8(&body).map_err(Error::NonUtf8Body)?;
 
@@ -355,11 +521,22 @@ where
                 default_time,
                 params.accept_partial,
                 params.precision,
+                Some(&source),
             )
             .await?;
 
+        if result.shed_lines > 0 {
+            self.shed_lines
+                .recorder([("db", result.db_name.to_string().into())])
+                .inc(result.shed_lines as u64);
+        }
+
         if result.invalid_lines.is_empty() {
-            Ok(Response::new(Body::empty()))
+            let mut response = Response::builder();
+            if result.shed_lines > 0 {
+                response = response.header(SHED_LINES_HEADER, result.shed_lines);
+            }
+            Ok(response.body(Body::empty()).unwrap())
         } else {
             Err(Error::PartialLpWrite(result))
         }
@@ -371,20 +548,23 @@ where
             query_str,
             format,
             params,
+            timeout_ms,
+            accept_partial,
         } = self.extract_query_request::<String>(req).await?;
 
         info!(%database, %query_str, ?format, "handling query_sql");
 
-        let stream = self
-            .query_executor
-            .query(&database, &query_str, params, QueryKind::Sql, None, None)
-            .await?;
+        let deadline = self.query_deadline(Some(&database), timeout_ms, accept_partial);
+        let stream = plan_with_deadline(deadline, async {
+            self.query_executor
+                .query(&database, &query_str, params, QueryKind::Sql, None, None)
+                .await
+                .map_err(Error::from)
+        })
+        .await?;
+        let (batches, truncated) = collect_with_deadline(stream, deadline).await?;
 
-        Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, format.as_content_type())
-            .body(record_batch_stream_to_body(stream, format).await?)
-            .map_err(Into::into)
+        query_response(batches, format, truncated)
     }
 
     async fn query_influxql(&self, req: Request<Body>) -> Result<Response<Body>> {
@@ -393,19 +573,240 @@ where
             query_str,
             format,
             params,
+            timeout_ms,
+            accept_partial,
         } = self.extract_query_request::<Option<String>>(req).await?;
 
         info!(?database, %query_str, ?format, "handling query_influxql");
 
-        let stream = self
-            .query_influxql_inner(database, &query_str, params)
-            .await?;
+        let deadline = self.query_deadline(database.as_deref(), timeout_ms, accept_partial);
+        let stream = plan_with_deadline(
+            deadline,
+            self.query_influxql_inner(database, &query_str, params),
+        )
+        .await?;
+        let (batches, truncated) = collect_with_deadline(stream, deadline).await?;
 
-        Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, format.as_content_type())
-            .body(record_batch_stream_to_body(stream, format).await?)
-            .map_err(Into::into)
+        query_response(batches, format, truncated)
+    }
+
+    /// The deadline for a `/api/v3/query_*` request, from the timeout in the request or else the
+    /// default timeout of the database being queried
+    fn query_deadline(
+        &self,
+        database: Option<&str>,
+        timeout_ms: Option<NonZeroU64>,
+        accept_partial: bool,
+    ) -> Option<QueryDeadline> {
+        let timeout = timeout_ms
+            .map(|timeout_ms| Duration::from_millis(timeout_ms.get()))
+            .or_else(|| {
+                database
+                    .and_then(|db| self.write_buffer.catalog().db_schema(db))
+                    .and_then(|db| db.query_timeout())
+            })?;
+
+        Some(QueryDeadline::new(timeout, accept_partial))
+    }
+
+    async fn configure_field_validation(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let FieldValidationRequest {
+            db,
+            table,
+            field,
+            rule,
+            action,
+        } = serde_json::from_slice(&body)?;
+
+        info!(%db, %table, %field, ?rule, ?action, "configure field validation");
+
+        let validation = rule.map(|rule| FieldValidation { rule, action });
+        self.write_buffer
+            .catalog()
+            .set_field_validation(&db, &table, &field, validation)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_query_timeout(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let QueryTimeoutRequest { db, timeout_ms } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, ?timeout_ms, "configure query timeout");
+
+        self.write_buffer
+            .catalog()
+            .set_query_timeout(&db, timeout_ms)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_retention(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let RetentionRequest {
+            db,
+            retention_period_ms,
+        } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, ?retention_period_ms, "configure retention period");
+
+        self.write_buffer
+            .catalog()
+            .set_retention_period(&db, retention_period_ms)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_provenance(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let ProvenanceRequest { db, enabled } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, enabled, "configure provenance columns");
+
+        self.write_buffer
+            .catalog()
+            .set_provenance_columns(&db, enabled)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_load_shedding(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let LoadSheddingRequest { db, shed_percent } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, ?shed_percent, "configure load shedding");
+
+        self.write_buffer
+            .catalog()
+            .set_load_shedding(&db, shed_percent)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_quarantine_rejected_writes(
+        &self,
+        req: Request<Body>,
+    ) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let QuarantineRejectedWritesRequest { db, enabled } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, enabled, "configure quarantine of rejected writes");
+
+        self.write_buffer
+            .catalog()
+            .set_quarantine_rejected_writes(&db, enabled)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_wal_replay(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let WalReplayRequest { db, replay_mode } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, ?replay_mode, "configure wal replay");
+
+        self.write_buffer
+            .catalog()
+            .set_replay_mode(&db, replay_mode)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_strict_tables(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let StrictTablesRequest { db, enabled } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, enabled, "configure strict tables");
+
+        self.write_buffer
+            .catalog()
+            .set_strict_tables(&db, enabled)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_table(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let CreateTableRequest { db, table, tags } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, %table, ?tags, "create table");
+
+        self.write_buffer.catalog().create_table(
+            &db,
+            &table,
+            &tags,
+            self.time_provider.now().timestamp_nanos(),
+        )?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_time_validation(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let TimeValidationRequest {
+            db,
+            precision,
+            max_future_seconds,
+            min_time,
+        } = serde_json::from_slice(&body)?;
+        validate_db_name(&db, false)?;
+
+        info!(%db, ?precision, ?max_future_seconds, ?min_time, "configure time validation");
+
+        let time_validation = TimeValidation {
+            precision,
+            max_future_seconds,
+            min_time,
+        };
+        let time_validation =
+            (time_validation != TimeValidation::default()).then_some(time_validation);
+        self.write_buffer
+            .catalog()
+            .set_time_validation(&db, time_validation)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn configure_tag_normalization(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let body = self.read_body(req).await?;
+        let TagNormalizationRequest {
+            db,
+            table,
+            normalization,
+        } = serde_json::from_slice(&body)?;
+
+        info!(%db, %table, ?normalization, "configure tag normalization");
+
+        let normalization = if normalization == TagNormalization::default() {
+            None
+        } else {
+            Some(normalization)
+        };
+        self.write_buffer
+            .catalog()
+            .set_tag_normalization(&db, &table, normalization)?;
+        self.write_buffer.persist_catalog().await?;
+
+        Ok(Response::new(Body::empty()))
     }
 
     fn health(&self) -> Result<Response<Body>> {
@@ -538,6 +939,8 @@ where
                     query_str: r.query_str,
                     format: r.format,
                     params: r.params.map(|s| serde_json::from_str(&s)).transpose()?,
+                    timeout_ms: r.timeout_ms,
+                    accept_partial: r.accept_partial,
                 }
             }
             Method::POST => {
@@ -552,6 +955,8 @@ where
             query_str: request.query_str,
             format: request.format.unwrap_or(header_format),
             params: request.params,
+            timeout_ms: request.timeout_ms,
+            accept_partial: request.accept_partial,
         })
     }
 
@@ -660,6 +1065,19 @@ fn validate_auth_header(header: HeaderValue) -> Result<Vec<u8>, AuthorizationErr
     Ok(token.as_bytes().to_vec())
 }
 
+impl From<AuthorizationError> for Error {
+    fn from(auth_error: AuthorizationError) -> Self {
+        match auth_error {
+            AuthorizationError::Unauthorized => Self::Unauthenticated,
+            AuthorizationError::MalformedRequest => Self::MalformedAuthorization,
+            AuthorizationError::Forbidden => Self::Forbidden,
+            // We don't expect this to happen, but if the header is messed up
+            // better to handle it then not at all
+            AuthorizationError::ToStr(e) => Self::ToStr(e),
+        }
+    }
+}
+
 impl From<authz::Error> for AuthorizationError {
     fn from(auth_error: authz::Error) -> Self {
         match auth_error {
@@ -747,6 +1165,11 @@ pub(crate) struct QueryRequest<D, F, P> {
     pub(crate) query_str: String,
     pub(crate) format: F,
     pub(crate) params: Option<P>,
+    /// Cancel the query if it runs longer than this, instead of the database's default timeout
+    pub(crate) timeout_ms: Option<NonZeroU64>,
+    /// On timeout, return the rows produced so far instead of an error
+    #[serde(default)]
+    pub(crate) accept_partial: bool,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -798,10 +1221,87 @@ impl QueryFormat {
     }
 }
 
-async fn record_batch_stream_to_body(
-    stream: Pin<Box<dyn RecordBatchStream + Send>>,
+/// Header set on query responses that were cut short by the query's timeout
+pub(crate) const QUERY_TRUNCATED_HEADER: &str = "x-influxdb-query-truncated";
+
+/// Header set on write responses with the number of lines dropped to shed load
+pub(crate) const SHED_LINES_HEADER: &str = "x-influxdb-shed-lines";
+
+/// The time limit on a query
+#[derive(Debug, Clone, Copy)]
+struct QueryDeadline {
+    timeout: Duration,
+    deadline: Instant,
+    accept_partial: bool,
+}
+
+impl QueryDeadline {
+    fn new(timeout: Duration, accept_partial: bool) -> Self {
+        Self {
+            timeout,
+            deadline: Instant::now() + timeout,
+            accept_partial,
+        }
+    }
+}
+
+/// Plans a query, failing if the deadline passes first. There are no rows to return from a
+/// query that hasn't been planned, so partial results don't apply here.
+async fn plan_with_deadline<F>(
+    deadline: Option<QueryDeadline>,
+    plan: F,
+) -> Result<SendableRecordBatchStream>
+where
+    F: Future<Output = Result<SendableRecordBatchStream>>,
+{
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline.deadline, plan)
+            .await
+            .map_err(|_| Error::QueryTimeout(deadline.timeout))?,
+        None => plan.await,
+    }
+}
+
+/// Collects the output of a query. If the deadline passes first, the stream is dropped, which
+/// cancels the query, and either the batches collected so far are returned along with `true` to
+/// mark them as truncated, or the query fails.
+async fn collect_with_deadline(
+    mut stream: SendableRecordBatchStream,
+    deadline: Option<QueryDeadline>,
+) -> Result<(Vec<RecordBatch>, bool)> {
+    let Some(deadline) = deadline else {
+        return Ok((stream.try_collect().await?, false));
+    };
+
+    let mut batches = vec![];
+    loop {
+        match tokio::time::timeout_at(deadline.deadline, stream.next()).await {
+            Ok(Some(batch)) => batches.push(batch?),
+            Ok(None) => return Ok((batches, false)),
+            Err(_) if deadline.accept_partial => return Ok((batches, true)),
+            Err(_) => return Err(Error::QueryTimeout(deadline.timeout)),
+        }
+    }
+}
+
+fn query_response(
+    batches: Vec<RecordBatch>,
     format: QueryFormat,
-) -> Result<Body, Error> {
+    truncated: bool,
+) -> Result<Response<Body>> {
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, format.as_content_type());
+    if truncated {
+        builder = builder.header(QUERY_TRUNCATED_HEADER, "true");
+    }
+
+    builder
+        .body(record_batches_to_body(batches, format)?)
+        .map_err(Into::into)
+}
+
+fn record_batches_to_body(batches: Vec<RecordBatch>, format: QueryFormat) -> Result<Body, Error> {
     fn to_json(batches: Vec<RecordBatch>) -> Result<Bytes> {
         let batches: Vec<&RecordBatch> = batches.iter().collect();
         // See https://github.com/influxdata/influxdb/issues/24981
@@ -839,8 +1339,6 @@ async fn record_batch_stream_to_body(
         Ok(Bytes::from(bytes))
     }
 
-    let batches = stream.try_collect::<Vec<RecordBatch>>().await?;
-
     match format {
         QueryFormat::Pretty => to_pretty(batches),
         QueryFormat::Parquet => to_parquet(batches),
@@ -863,6 +1361,120 @@ pub(crate) struct WriteParams {
     pub(crate) precision: Precision,
 }
 
+/// Identifies the writer of a request for the `_source` provenance column. Tokens are never
+/// recorded, only a fingerprint of them.
+fn write_source(req: &Request<Body>) -> String {
+    use sha2::{Digest, Sha256};
+
+    match req
+        .extensions()
+        .get::<AuthorizationHeaderExtension>()
+        .and_then(|header| header.as_ref())
+    {
+        Some(header) => {
+            let digest = Sha256::digest(header.as_bytes());
+            format!("token:{}", hex::encode(&digest[..8]))
+        }
+        None => "anonymous".to_string(),
+    }
+}
+
+/// Request body for the `/api/v3/configure/query_timeout` API. Omitting the timeout removes the
+/// database's default. The timeout only applies to the `/api/v3/query_sql` and
+/// `/api/v3/query_influxql` APIs, not to Flight or the v1 `/query` API.
+#[derive(Debug, Deserialize)]
+pub(crate) struct QueryTimeoutRequest {
+    pub(crate) db: String,
+    pub(crate) timeout_ms: Option<NonZeroU64>,
+}
+
+/// Request body for the `/api/v3/configure/retention` API. Queries leave out rows older than the
+/// retention period. Omitting the retention period keeps data forever.
+#[derive(Debug, Deserialize)]
+pub(crate) struct RetentionRequest {
+    pub(crate) db: String,
+    pub(crate) retention_period_ms: Option<NonZeroU64>,
+}
+
+/// Request body for the `/api/v3/configure/provenance` API
+#[derive(Debug, Deserialize)]
+pub(crate) struct ProvenanceRequest {
+    pub(crate) db: String,
+    pub(crate) enabled: bool,
+}
+
+/// Request body for the `/api/v3/configure/load_shedding` API. Omitting the percentage stops
+/// dropping lines.
+#[derive(Debug, Deserialize)]
+pub(crate) struct LoadSheddingRequest {
+    pub(crate) db: String,
+    pub(crate) shed_percent: Option<NonZeroU8>,
+}
+
+/// Request body for the `/api/v3/configure/quarantine_rejected_writes` API
+#[derive(Debug, Deserialize)]
+pub(crate) struct QuarantineRejectedWritesRequest {
+    pub(crate) db: String,
+    pub(crate) enabled: bool,
+}
+
+/// Request body for the `/api/v3/configure/wal_replay` API. The replay mode is `full`, `skip`, or
+/// a duration such as `30m`, and omitting it replays all of the database's writes. The
+/// `--wal-replay` server option overrides it.
+#[derive(Debug, Deserialize)]
+pub(crate) struct WalReplayRequest {
+    pub(crate) db: String,
+    pub(crate) replay_mode: Option<ReplayMode>,
+}
+
+/// Request body for the `/api/v3/configure/strict_tables` API
+#[derive(Debug, Deserialize)]
+pub(crate) struct StrictTablesRequest {
+    pub(crate) db: String,
+    pub(crate) enabled: bool,
+}
+
+/// Request body for the `/api/v3/configure/table` API. Fields are added to the table by writes.
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateTableRequest {
+    pub(crate) db: String,
+    pub(crate) table: String,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+}
+
+/// Request body for the `/api/v3/configure/tag_normalization` API. Leaving every option unset
+/// removes the normalization from the table.
+#[derive(Debug, Deserialize)]
+pub(crate) struct TagNormalizationRequest {
+    pub(crate) db: String,
+    pub(crate) table: String,
+    #[serde(flatten)]
+    pub(crate) normalization: TagNormalization,
+}
+
+/// Request body for the `/api/v3/configure/time_validation` API. Omitting all of the limits
+/// removes them from the database.
+#[derive(Debug, Deserialize)]
+pub(crate) struct TimeValidationRequest {
+    pub(crate) db: String,
+    pub(crate) precision: Option<Precision>,
+    pub(crate) max_future_seconds: Option<u64>,
+    pub(crate) min_time: Option<i64>,
+}
+
+/// Request body for the `/api/v3/configure/field_validation` API. Omitting the `rule` removes any
+/// validation from the field.
+#[derive(Debug, Deserialize)]
+pub(crate) struct FieldValidationRequest {
+    pub(crate) db: String,
+    pub(crate) table: String,
+    pub(crate) field: String,
+    pub(crate) rule: Option<FieldRule>,
+    #[serde(default)]
+    pub(crate) action: ValidationAction,
+}
+
 impl From<iox_http::write::WriteParams> for WriteParams {
     fn from(legacy: iox_http::write::WriteParams) -> Self {
         Self {
@@ -882,36 +1494,7 @@ where
     Error: From<<Q as QueryExecutor>::Error>,
 {
     if let Err(e) = http_server.authorize_request(&mut req).await {
-        match e {
-            AuthorizationError::Unauthorized => {
-                return Ok(Response::builder()
-                    .status(StatusCode::UNAUTHORIZED)
-                    .body(Body::empty())
-                    .unwrap())
-            }
-            AuthorizationError::MalformedRequest => {
-                return Ok(Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(Body::from("{\"error\":\
-                        \"Authorization header was malformed and should be in the form 'Authorization: Bearer <token>'\"\
-                    }"))
-                    .unwrap());
-            }
-            AuthorizationError::Forbidden => {
-                return Ok(Response::builder()
-                    .status(StatusCode::FORBIDDEN)
-                    .body(Body::empty())
-                    .unwrap())
-            }
-            // We don't expect this to happen, but if the header is messed up
-            // better to handle it then not at all
-            AuthorizationError::ToStr(_) => {
-                return Ok(Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Body::empty())
-                    .unwrap())
-            }
-        }
+        return Ok(Error::from(e).into_response());
     }
     debug!(request = ?req,"Processing request");
 
@@ -941,6 +1524,35 @@ where
         (Method::GET | Method::POST, "/api/v3/query_influxql") => {
             http_server.query_influxql(req).await
         }
+        (Method::POST, "/api/v3/configure/retention") => http_server.configure_retention(req).await,
+        (Method::POST, "/api/v3/configure/query_timeout") => {
+            http_server.configure_query_timeout(req).await
+        }
+        (Method::POST, "/api/v3/configure/provenance") => {
+            http_server.configure_provenance(req).await
+        }
+        (Method::POST, "/api/v3/configure/load_shedding") => {
+            http_server.configure_load_shedding(req).await
+        }
+        (Method::POST, "/api/v3/configure/quarantine_rejected_writes") => {
+            http_server.configure_quarantine_rejected_writes(req).await
+        }
+        (Method::POST, "/api/v3/configure/wal_replay") => {
+            http_server.configure_wal_replay(req).await
+        }
+        (Method::POST, "/api/v3/configure/strict_tables") => {
+            http_server.configure_strict_tables(req).await
+        }
+        (Method::POST, "/api/v3/configure/table") => http_server.configure_table(req).await,
+        (Method::POST, "/api/v3/configure/field_validation") => {
+            http_server.configure_field_validation(req).await
+        }
+        (Method::POST, "/api/v3/configure/time_validation") => {
+            http_server.configure_time_validation(req).await
+        }
+        (Method::POST, "/api/v3/configure/tag_normalization") => {
+            http_server.configure_tag_normalization(req).await
+        }
         (Method::GET, "/query") => http_server.v1_query(req).await,
         (Method::GET, "/health" | "/api/v1/health") => http_server.health(),
         (Method::GET | Method::POST, "/ping") => http_server.ping(),
@@ -968,24 +1580,34 @@ where
 }
 
 fn legacy_write_error_to_response(e: WriteParseError) -> Response<Body> {
-    let err: ErrorMessage<()> = ErrorMessage {
-        error: e.to_string(),
-        data: None,
-    };
-    let serialized = serde_json::to_string(&err).unwrap();
-    let body = Body::from(serialized);
+    let err: ErrorMessage<()> = ErrorMessage::new(ErrorCode::InvalidRequest, e.to_string(), None);
     let status = match e {
         WriteParseError::NotImplemented => StatusCode::NOT_FOUND,
         WriteParseError::SingleTenantError(e) => StatusCode::from(&e),
         WriteParseError::MultiTenantError(e) => StatusCode::from(&e),
     };
-    Response::builder().status(status).body(body).unwrap()
+    err.into_response(status)
 }
 
 #[cfg(test)]
 mod tests {
     use super::validate_db_name;
     use super::ValidateDbNameError;
+    use super::{collect_with_deadline, QueryDeadline};
+    use super::{
+        AuthorizationError, CatalogError, Error, ErrorCode, ErrorMessage, WriteBufferError,
+    };
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use data_types::ColumnType;
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use datafusion::physical_plan::SendableRecordBatchStream;
+    use futures::StreamExt;
+    use hyper::StatusCode;
+    use influxdb3_write::persister::Error as PersisterError;
+    use std::sync::Arc;
+    use std::time::Duration;
 
     macro_rules! assert_validate_db_name {
         ($name:literal, $accept_rp:literal, $expected:pat) => {
@@ -1013,4 +1635,132 @@ mod tests {
         assert_validate_db_name!("_foo", false, Err(ValidateDbNameError::InvalidStartChar));
         assert_validate_db_name!("", false, Err(ValidateDbNameError::Empty));
     }
+
+    #[test]
+    fn error_codes() {
+        let code = Error::DbName(ValidateDbNameError::Empty).code();
+        assert_eq!(code, ErrorCode::InvalidDatabaseName);
+        assert!(!code.is_retryable());
+        assert_eq!(Error::RequestLimit.code(), ErrorCode::Overloaded);
+        assert!(Error::RequestLimit.code().is_retryable());
+        let backlog = Error::WriteBuffer(WriteBufferError::PersistBacklog {
+            persisting: 3,
+            limit: 2,
+        });
+        assert_eq!(backlog.code(), ErrorCode::Overloaded);
+        assert_eq!(Error::MissingQueryParams.code(), ErrorCode::InvalidRequest);
+        assert_eq!(Error::Forbidden.code(), ErrorCode::Unauthorized);
+        assert_eq!(
+            Error::Catalog(CatalogError::TooManyDbs).code(),
+            ErrorCode::LimitExceeded
+        );
+
+        // errors wrapped by the write buffer are coded by the error they wrap
+        let not_found = CatalogError::DatabaseNotFound("foo".to_string());
+        let code = Error::WriteBuffer(WriteBufferError::CatalogUpdateError(not_found)).code();
+        assert_eq!(code, ErrorCode::NotFound);
+        assert!(!code.is_retryable());
+        let mismatch = Error::WriteBuffer(WriteBufferError::ColumnTypeMismatch {
+            name: "usage".to_string(),
+            existing: ColumnType::F64,
+            new: ColumnType::I64,
+        });
+        assert_eq!(mismatch.code(), ErrorCode::SchemaMismatch);
+        let fenced = Error::WriteBuffer(WriteBufferError::PersisterError(PersisterError::Fenced {
+            held: 1,
+            current: 2,
+        }));
+        assert_eq!(fenced.code(), ErrorCode::PersistenceFailed);
+        assert!(!fenced.code().is_retryable());
+
+        // the error body carries the SQLSTATE for the code
+        let err: ErrorMessage<()> = ErrorMessage::new(ErrorCode::QueryTimeout, "timed out", None);
+        let body: serde_json::Value = serde_json::to_value(&err).unwrap();
+        assert_eq!(body["code"], "query_timeout");
+        assert_eq!(body["sqlstate"], "57014");
+
+        // codes are part of the API, so their serialized form must not change
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::InvalidLineProtocol).unwrap(),
+            "\"invalid_line_protocol\""
+        );
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::LimitExceeded).unwrap(),
+            "\"limit_exceeded\""
+        );
+    }
+
+    #[tokio::test]
+    async fn authorization_errors_have_error_bodies() {
+        for (err, status, code) in [
+            (
+                AuthorizationError::Unauthorized,
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+            ),
+            (
+                AuthorizationError::Forbidden,
+                StatusCode::FORBIDDEN,
+                "unauthorized",
+            ),
+            (
+                AuthorizationError::MalformedRequest,
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+            ),
+        ] {
+            let response = Error::from(err).into_response();
+            assert_eq!(response.status(), status);
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], code);
+            assert_eq!(body["retryable"], false);
+        }
+    }
+
+    #[tokio::test]
+    async fn query_deadline() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(Int64Array::from(vec![1, 2, 3]))],
+        )
+        .unwrap();
+        // a query that produces one batch and then either finishes or never finishes
+        let query = |finishes: bool| -> SendableRecordBatchStream {
+            let rest = if finishes {
+                futures::stream::empty().boxed()
+            } else {
+                futures::stream::pending().boxed()
+            };
+            let batches = futures::stream::iter([Ok(batch.clone())]).chain(rest);
+            Box::pin(RecordBatchStreamAdapter::new(Arc::clone(&schema), batches))
+        };
+        let deadline = |accept_partial| {
+            Some(QueryDeadline::new(
+                Duration::from_millis(10),
+                accept_partial,
+            ))
+        };
+
+        let (batches, truncated) = collect_with_deadline(query(false), deadline(true))
+            .await
+            .unwrap();
+        assert!(truncated);
+        assert_eq!(batches, vec![batch.clone()]);
+
+        let err = collect_with_deadline(query(false), deadline(false))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::QueryTimeout(_)));
+        assert_eq!(err.code(), ErrorCode::QueryTimeout);
+        assert!(!err.code().is_retryable());
+
+        // queries that finish in time aren't marked as truncated
+        let (batches, truncated) = collect_with_deadline(query(true), deadline(true))
+            .await
+            .unwrap();
+        assert!(!truncated);
+        assert_eq!(batches, vec![batch]);
+    }
 }
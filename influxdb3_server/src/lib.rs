@@ -239,7 +239,7 @@ mod tests {
     use pretty_assertions::assert_eq;
     use std::collections::HashMap;
     use std::net::{SocketAddr, SocketAddrV4};
-    use std::num::NonZeroUsize;
+    use std::num::{NonZeroU64, NonZeroUsize};
     use std::sync::atomic::{AtomicU16, Ordering};
     use std::sync::Arc;
     use tokio_util::sync::CancellationToken;
@@ -279,6 +279,7 @@ mod tests {
                 Arc::clone(&time_provider),
                 SegmentDuration::new_5m(),
                 Arc::clone(&exec),
+                None,
             )
             .await
             .unwrap(),
@@ -439,6 +440,7 @@ mod tests {
                 Arc::clone(&time_provider),
                 SegmentDuration::new_5m(),
                 Arc::clone(&exec),
+                None,
             )
             .await
             .unwrap(),
@@ -486,6 +488,9 @@ mod tests {
             body,
             "{\
                 \"error\":\"parsing failed for write_lp endpoint\",\
+                \"code\":\"invalid_line_protocol\",\
+                \"sqlstate\":\"22000\",\
+                \"retryable\":false,\
                 \"data\":{\
                     \"original_line\":\"cpu,host=a val= 123\",\
                     \"line_number\":1,\
@@ -513,6 +518,9 @@ mod tests {
             body,
             "{\
                 \"error\":\"partial write of line protocol occurred\",\
+                \"code\":\"partial_write\",\
+                \"sqlstate\":\"22000\",\
+                \"retryable\":false,\
                 \"data\":[{\
                     \"original_line\":\"cpu,host=a val= 123\",\
                     \"line_number\":2,\
@@ -558,6 +566,9 @@ mod tests {
             body,
             "{\
                 \"error\":\"invalid character in database name: must be ASCII, containing only letters, numbers, underscores, or hyphens\",\
+                \"code\":\"invalid_database_name\",\
+                \"sqlstate\":\"3D000\",\
+                \"retryable\":false,\
                 \"data\":null\
             }"
         );
@@ -581,6 +592,9 @@ mod tests {
             body,
             "{\
                 \"error\":\"db name did not start with a number or letter\",\
+                \"code\":\"invalid_database_name\",\
+                \"sqlstate\":\"3D000\",\
+                \"retryable\":false,\
                 \"data\":null\
             }"
         );
@@ -604,6 +618,9 @@ mod tests {
             body,
             "{\
                 \"error\":\"db name cannot be empty\",\
+                \"code\":\"invalid_database_name\",\
+                \"sqlstate\":\"3D000\",\
+                \"retryable\":false,\
                 \"data\":null\
             }"
         );
@@ -646,6 +663,7 @@ mod tests {
                 Arc::clone(&time_provider),
                 SegmentDuration::new_5m(),
                 Arc::clone(&exec),
+                None,
             )
             .await
             .unwrap(),
@@ -786,6 +804,251 @@ mod tests {
         shutdown.cancel();
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn parquet_file_system_tables() {
+        let addr = get_free_port();
+        let trace_header_parser = trace_http::ctx::TraceHeaderParser::new();
+        let metrics = Arc::new(metric::Registry::new());
+        let common_state =
+            crate::CommonServerState::new(Arc::clone(&metrics), None, trace_header_parser, addr)
+                .unwrap();
+        let object_store: Arc<DynObjectStore> = Arc::new(object_store::memory::InMemory::new());
+        let parquet_store =
+            ParquetStorage::new(Arc::clone(&object_store), StorageId::from("influxdb3"));
+        let exec = Arc::new(Executor::new_with_config_and_executor(
+            ExecutorConfig {
+                target_query_partitions: NonZeroUsize::new(1).unwrap(),
+                object_stores: [&parquet_store]
+                    .into_iter()
+                    .map(|store| (store.id(), Arc::clone(store.object_store())))
+                    .collect(),
+                metric_registry: Arc::clone(&metrics),
+                mem_pool_size: usize::MAX,
+            },
+            DedicatedExecutor::new_testing(),
+        ));
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+
+        let write_buffer = Arc::new(
+            influxdb3_write::write_buffer::WriteBufferImpl::new(
+                Arc::clone(&persister),
+                None::<Arc<influxdb3_write::wal::WalImpl>>,
+                Arc::clone(&time_provider),
+                SegmentDuration::new_5m(),
+                Arc::clone(&exec),
+                None,
+            )
+            .await
+            .unwrap(),
+        );
+        let query_executor = crate::query_executor::QueryExecutorImpl::new(
+            write_buffer.catalog(),
+            Arc::clone(&write_buffer),
+            Arc::clone(&exec),
+            Arc::clone(&metrics),
+            Arc::new(HashMap::new()),
+            10,
+            10,
+        );
+
+        let server = ServerBuilder::new(common_state)
+            .write_buffer(Arc::clone(&write_buffer))
+            .query_executor(Arc::new(query_executor))
+            .persister(persister)
+            .authorizer(Arc::new(DefaultAuthorizer))
+            .time_provider(Arc::clone(&time_provider))
+            .build();
+        let frontend_shutdown = CancellationToken::new();
+        let shutdown = frontend_shutdown.clone();
+
+        tokio::spawn(async move { serve(server, frontend_shutdown).await });
+
+        let server = format!("http://{}", addr);
+        write_lp(
+            &server,
+            "foo",
+            "cpu,host=a val=1i 10\n\
+             cpu,host=b val=2i 20\n\
+             cpu,host=a val=3i 30",
+            None,
+            false,
+            "nanosecond",
+        )
+        .await;
+
+        // advance the time so the segment is closed and wait for it to persist
+        time_provider.set(Time::from_timestamp(800, 0).unwrap());
+        let files = loop {
+            let res = query(
+                &server,
+                "foo",
+                "select table_name, size_bytes, row_count, arrow_size_bytes, compression_ratio \
+                 from system.parquet_files",
+                "json",
+                None,
+            )
+            .await;
+            let body = body::to_bytes(res.into_body()).await.unwrap();
+            let files: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+            if !files.is_empty() {
+                break files;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        };
+
+        assert_eq!(files.len(), 1);
+        let file = &files[0];
+        assert_eq!(file["table_name"], "cpu");
+        assert_eq!(file["row_count"], 3);
+        let size_bytes = file["size_bytes"].as_u64().unwrap();
+        let arrow_size_bytes = file["arrow_size_bytes"].as_u64().unwrap();
+        assert!(size_bytes > 0);
+        assert!(arrow_size_bytes > 0);
+        let ratio = file["compression_ratio"].as_f64().unwrap();
+        assert!((ratio - arrow_size_bytes as f64 / size_bytes as f64).abs() < f64::EPSILON);
+
+        let res = query(
+            &server,
+            "foo",
+            "select table_name, column_name, arrow_size_bytes, parquet_size_bytes, \
+             compression_ratio from system.parquet_file_columns order by column_name",
+            "json",
+            None,
+        )
+        .await;
+        let body = body::to_bytes(res.into_body()).await.unwrap();
+        let columns: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        let column_names = columns
+            .iter()
+            .map(|column| column["column_name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(column_names, ["host", "time", "val"]);
+
+        let mut column_arrow_bytes = 0;
+        for column in &columns {
+            assert_eq!(column["table_name"], "cpu");
+            let arrow_bytes = column["arrow_size_bytes"].as_u64().unwrap();
+            let parquet_bytes = column["parquet_size_bytes"].as_u64().unwrap();
+            assert!(arrow_bytes > 0);
+            assert!(parquet_bytes > 0);
+            let ratio = column["compression_ratio"].as_f64().unwrap();
+            assert!((ratio - arrow_bytes as f64 / parquet_bytes as f64).abs() < f64::EPSILON);
+            column_arrow_bytes += arrow_bytes;
+        }
+        // the file's arrow size is the sum of its columns
+        assert_eq!(column_arrow_bytes, arrow_size_bytes);
+
+        shutdown.cancel();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn query_leaves_out_rows_past_retention() {
+        let addr = get_free_port();
+        let trace_header_parser = trace_http::ctx::TraceHeaderParser::new();
+        let metrics = Arc::new(metric::Registry::new());
+        let common_state =
+            crate::CommonServerState::new(Arc::clone(&metrics), None, trace_header_parser, addr)
+                .unwrap();
+        let object_store: Arc<DynObjectStore> = Arc::new(object_store::memory::InMemory::new());
+        let parquet_store =
+            ParquetStorage::new(Arc::clone(&object_store), StorageId::from("influxdb3"));
+        let exec = Arc::new(Executor::new_with_config_and_executor(
+            ExecutorConfig {
+                target_query_partitions: NonZeroUsize::new(1).unwrap(),
+                object_stores: [&parquet_store]
+                    .into_iter()
+                    .map(|store| (store.id(), Arc::clone(store.object_store())))
+                    .collect(),
+                metric_registry: Arc::clone(&metrics),
+                mem_pool_size: usize::MAX,
+            },
+            DedicatedExecutor::new_testing(),
+        ));
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+
+        let write_buffer = Arc::new(
+            influxdb3_write::write_buffer::WriteBufferImpl::new(
+                Arc::clone(&persister),
+                None::<Arc<influxdb3_write::wal::WalImpl>>,
+                Arc::clone(&time_provider),
+                SegmentDuration::new_5m(),
+                Arc::clone(&exec),
+                None,
+            )
+            .await
+            .unwrap(),
+        );
+        let query_executor = crate::query_executor::QueryExecutorImpl::new(
+            write_buffer.catalog(),
+            Arc::clone(&write_buffer),
+            Arc::clone(&exec),
+            Arc::clone(&metrics),
+            Arc::new(HashMap::new()),
+            10,
+            10,
+        );
+
+        let server = ServerBuilder::new(common_state)
+            .write_buffer(Arc::clone(&write_buffer))
+            .query_executor(Arc::new(query_executor))
+            .persister(persister)
+            .authorizer(Arc::new(DefaultAuthorizer))
+            .time_provider(Arc::clone(&time_provider))
+            .build();
+        let frontend_shutdown = CancellationToken::new();
+        let shutdown = frontend_shutdown.clone();
+
+        tokio::spawn(async move { serve(server, frontend_shutdown).await });
+
+        // both rows are in the segment from 0s to 5m
+        let server = format!("http://{}", addr);
+        write_lp(
+            &server,
+            "foo",
+            "cpu,host=a usage=1 10\n\
+             cpu,host=b usage=2 200",
+            None,
+            false,
+            "second",
+        )
+        .await;
+
+        // with a retention period of 100s at 250s, only the row at 200s is kept
+        time_provider.set(Time::from_timestamp(250, 0).unwrap());
+        write_buffer
+            .catalog()
+            .set_retention_period("foo", NonZeroU64::new(100_000))
+            .unwrap();
+
+        let res = query(
+            &server,
+            "foo",
+            "select host, usage from cpu",
+            "pretty",
+            None,
+        )
+        .await;
+        let body = body::to_bytes(res.into_body()).await.unwrap();
+        let body = String::from_utf8(body.as_bytes().to_vec()).unwrap();
+        let expected = vec![
+            "+------+-------+",
+            "| host | usage |",
+            "+------+-------+",
+            "| b    | 2.0   |",
+            "+------+-------+",
+        ];
+        let actual: Vec<_> = body.split('\n').collect();
+        assert_eq!(
+            expected, actual,
+            "\n\nexpected:\n\n{:#?}\nactual:\n\n{:#?}\n\n",
+            expected, actual
+        );
+
+        shutdown.cancel();
+    }
+
     pub(crate) async fn write_lp(
         server: impl Into<String> + Send,
         database: impl Into<String> + Send,
@@ -1,14 +1,14 @@
 //! module for query executor
 use crate::{QueryExecutor, QueryKind};
 use arrow::array::{
-    ArrayRef, BooleanArray, DurationNanosecondArray, Int64Array, Int64Builder, StringBuilder,
-    StructArray, TimestampNanosecondArray,
+    ArrayRef, BooleanArray, DurationNanosecondArray, Float64Array, Int64Array, Int64Builder,
+    StringBuilder, StructArray, TimestampNanosecondArray, UInt64Array,
 };
 use arrow::datatypes::SchemaRef;
 use arrow::record_batch::RecordBatch;
 use arrow_schema::{ArrowError, TimeUnit};
 use async_trait::async_trait;
-use data_types::NamespaceId;
+use data_types::{ColumnType, NamespaceId};
 use datafusion::catalog::schema::SchemaProvider;
 use datafusion::catalog::CatalogProvider;
 use datafusion::common::arrow::array::StringArray;
@@ -24,7 +24,7 @@ use datafusion_util::config::DEFAULT_SCHEMA;
 use datafusion_util::MemoryStream;
 use influxdb3_write::{
     catalog::{Catalog, DatabaseSchema},
-    WriteBuffer,
+    ChunkContainer, ParquetFile, WriteBuffer,
 };
 use iox_query::exec::{Executor, IOxSessionContext, QueryConfig};
 use iox_query::frontend::sql::SqlQueryPlanner;
@@ -350,7 +350,9 @@ impl<B: WriteBuffer> Database<B> {
         query_log: Arc<QueryLog>,
     ) -> Self {
         let system_schema_provider = Arc::new(SystemSchemaProvider::new(
+            db_schema.name.clone(),
             write_buffer.catalog(),
+            Arc::clone(&write_buffer) as _,
             Arc::clone(&query_log),
         ));
         Self {
@@ -541,7 +543,10 @@ impl<B: WriteBuffer> TableProvider for QueryTable<B> {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> datafusion::common::Result<Arc<dyn ExecutionPlan>> {
-        let filters = filters.to_vec();
+        let mut filters = filters.to_vec();
+        // the retention filter is only added here, so the chunks are pruned by the same cutoff
+        // that leaves out expired rows sharing a buffer or file with retained ones
+        filters.extend(self.write_buffer.retention_filter(&self.db_schema.name));
         info!(
             "TableProvider scan {:?} {:?} {:?}",
             projection, filters, limit
@@ -565,7 +570,9 @@ impl<B: WriteBuffer> TableProvider for QueryTable<B> {
 pub const SYSTEM_SCHEMA: &str = "system";
 
 const QUERIES_TABLE: &str = "queries";
-const _PARQUET_FILES_TABLE: &str = "parquet_files";
+const SCHEMA_CHANGES_TABLE: &str = "schema_changes";
+const PARQUET_FILES_TABLE: &str = "parquet_files";
+const PARQUET_FILE_COLUMNS_TABLE: &str = "parquet_file_columns";
 
 struct SystemSchemaProvider {
     tables: HashMap<&'static str, Arc<dyn TableProvider>>,
@@ -583,12 +590,31 @@ impl std::fmt::Debug for SystemSchemaProvider {
 }
 
 impl SystemSchemaProvider {
-    fn new(_catalog: Arc<Catalog>, query_log: Arc<QueryLog>) -> Self {
+    fn new(
+        db_name: String,
+        catalog: Arc<Catalog>,
+        buffer: Arc<dyn ChunkContainer>,
+        query_log: Arc<QueryLog>,
+    ) -> Self {
         let mut tables = HashMap::<&'static str, Arc<dyn TableProvider>>::new();
         let queries = Arc::new(SystemTableProvider::new(Arc::new(QueriesTable::new(
             query_log,
         ))));
         tables.insert(QUERIES_TABLE, queries);
+        let parquet_files = Arc::new(SystemTableProvider::new(Arc::new(ParquetFilesTable::new(
+            db_name.clone(),
+            Arc::clone(&catalog),
+            Arc::clone(&buffer),
+        ))));
+        tables.insert(PARQUET_FILES_TABLE, parquet_files);
+        let parquet_file_columns = Arc::new(SystemTableProvider::new(Arc::new(
+            ParquetFileColumnsTable::new(db_name.clone(), Arc::clone(&catalog), buffer),
+        )));
+        tables.insert(PARQUET_FILE_COLUMNS_TABLE, parquet_file_columns);
+        let schema_changes = Arc::new(SystemTableProvider::new(Arc::new(SchemaChangesTable::new(
+            db_name, catalog,
+        ))));
+        tables.insert(SCHEMA_CHANGES_TABLE, schema_changes);
         Self { tables }
     }
 }
@@ -828,3 +854,351 @@ fn from_query_log_entries(
     let batch = RecordBatch::try_new(schema, columns)?;
     Ok(batch)
 }
+
+/// Lists the columns added to each table in a database, oldest first
+struct SchemaChangesTable {
+    schema: SchemaRef,
+    db_name: String,
+    catalog: Arc<Catalog>,
+}
+
+impl SchemaChangesTable {
+    fn new(db_name: String, catalog: Arc<Catalog>) -> Self {
+        Self {
+            schema: schema_changes_schema(),
+            db_name,
+            catalog,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IoxSystemTable for SchemaChangesTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        _filters: Option<Vec<Expr>>,
+        _limit: Option<usize>,
+    ) -> Result<RecordBatch, DataFusionError> {
+        // read the schema from the catalog so changes made since the query started are included
+        let Some(db_schema) = self.catalog.db_schema(&self.db_name) else {
+            return Ok(RecordBatch::new_empty(self.schema()));
+        };
+
+        let changes = db_schema
+            .table_names()
+            .iter()
+            .filter_map(|table_name| db_schema.get_table(table_name))
+            .flat_map(|table| {
+                table
+                    .schema_changes()
+                    .iter()
+                    .map(move |change| (table.name.as_str(), change))
+            })
+            .collect::<Vec<_>>();
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(
+                changes
+                    .iter()
+                    .map(|(table_name, _)| Some(*table_name))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                changes
+                    .iter()
+                    .map(|(_, change)| Some(change.column.as_str()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                changes
+                    .iter()
+                    .map(|(_, change)| {
+                        ColumnType::try_from(change.column_type)
+                            .ok()
+                            .map(|t| t.as_str())
+                    })
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                changes
+                    .iter()
+                    .map(|(_, change)| Some(change.time))
+                    .collect::<TimestampNanosecondArray>(),
+            ),
+            Arc::new(
+                changes
+                    .iter()
+                    .map(|(_, change)| Some(change.origin.as_str()))
+                    .collect::<StringArray>(),
+            ),
+        ];
+
+        Ok(RecordBatch::try_new(self.schema(), columns)?)
+    }
+}
+
+fn schema_changes_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("column_name", DataType::Utf8, false),
+        Field::new("column_type", DataType::Utf8, true),
+        Field::new(
+            "time",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ),
+        Field::new("origin", DataType::Utf8, false),
+    ];
+
+    Arc::new(DatafusionSchema::new(columns))
+}
+
+/// Lists the parquet files persisted for each table in a database, with how well their data
+/// compressed
+struct ParquetFilesTable {
+    schema: SchemaRef,
+    db_name: String,
+    catalog: Arc<Catalog>,
+    buffer: Arc<dyn ChunkContainer>,
+}
+
+impl ParquetFilesTable {
+    fn new(db_name: String, catalog: Arc<Catalog>, buffer: Arc<dyn ChunkContainer>) -> Self {
+        Self {
+            schema: parquet_files_schema(),
+            db_name,
+            catalog,
+            buffer,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IoxSystemTable for ParquetFilesTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        _filters: Option<Vec<Expr>>,
+        _limit: Option<usize>,
+    ) -> Result<RecordBatch, DataFusionError> {
+        let files = table_parquet_files(&self.db_name, &self.catalog, self.buffer.as_ref());
+
+        // files persisted before column sizes were recorded have no arrow size
+        let arrow_sizes = files
+            .iter()
+            .map(|(_, file)| {
+                (!file.column_sizes.is_empty()).then(|| {
+                    file.column_sizes
+                        .values()
+                        .map(|s| s.arrow_bytes)
+                        .sum::<u64>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(
+                files
+                    .iter()
+                    .map(|(table_name, _)| Some(table_name.as_str()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                files
+                    .iter()
+                    .map(|(_, file)| Some(file.path.as_str()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                files
+                    .iter()
+                    .map(|(_, file)| Some(file.size_bytes))
+                    .collect::<UInt64Array>(),
+            ),
+            Arc::new(
+                files
+                    .iter()
+                    .map(|(_, file)| Some(file.row_count))
+                    .collect::<UInt64Array>(),
+            ),
+            Arc::new(
+                files
+                    .iter()
+                    .map(|(_, file)| Some(file.min_time))
+                    .collect::<TimestampNanosecondArray>(),
+            ),
+            Arc::new(
+                files
+                    .iter()
+                    .map(|(_, file)| Some(file.max_time))
+                    .collect::<TimestampNanosecondArray>(),
+            ),
+            Arc::new(arrow_sizes.iter().copied().collect::<UInt64Array>()),
+            Arc::new(
+                files
+                    .iter()
+                    .zip(&arrow_sizes)
+                    .map(|((_, file), arrow_size)| {
+                        arrow_size.and_then(|a| compression_ratio(a, file.size_bytes))
+                    })
+                    .collect::<Float64Array>(),
+            ),
+        ];
+
+        Ok(RecordBatch::try_new(self.schema(), columns)?)
+    }
+}
+
+fn parquet_files_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("path", DataType::Utf8, false),
+        Field::new("size_bytes", DataType::UInt64, false),
+        Field::new("row_count", DataType::UInt64, false),
+        Field::new(
+            "min_time",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ),
+        Field::new(
+            "max_time",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ),
+        Field::new("arrow_size_bytes", DataType::UInt64, true),
+        Field::new("compression_ratio", DataType::Float64, true),
+    ];
+
+    Arc::new(DatafusionSchema::new(columns))
+}
+
+/// Lists the size of each column in the parquet files persisted for a database
+struct ParquetFileColumnsTable {
+    schema: SchemaRef,
+    db_name: String,
+    catalog: Arc<Catalog>,
+    buffer: Arc<dyn ChunkContainer>,
+}
+
+impl ParquetFileColumnsTable {
+    fn new(db_name: String, catalog: Arc<Catalog>, buffer: Arc<dyn ChunkContainer>) -> Self {
+        Self {
+            schema: parquet_file_columns_schema(),
+            db_name,
+            catalog,
+            buffer,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IoxSystemTable for ParquetFileColumnsTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        _filters: Option<Vec<Expr>>,
+        _limit: Option<usize>,
+    ) -> Result<RecordBatch, DataFusionError> {
+        let files = table_parquet_files(&self.db_name, &self.catalog, self.buffer.as_ref());
+        let sizes = files
+            .iter()
+            .flat_map(|(table_name, file)| {
+                file.column_sizes
+                    .iter()
+                    .map(move |(column_name, size)| (table_name, &file.path, column_name, size))
+            })
+            .collect::<Vec<_>>();
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(
+                sizes
+                    .iter()
+                    .map(|(table_name, ..)| Some(table_name.as_str()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                sizes
+                    .iter()
+                    .map(|(_, path, ..)| Some(path.as_str()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                sizes
+                    .iter()
+                    .map(|(_, _, column_name, _)| Some(column_name.as_str()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                sizes
+                    .iter()
+                    .map(|(.., size)| Some(size.arrow_bytes))
+                    .collect::<UInt64Array>(),
+            ),
+            Arc::new(
+                sizes
+                    .iter()
+                    .map(|(.., size)| Some(size.parquet_bytes))
+                    .collect::<UInt64Array>(),
+            ),
+            Arc::new(
+                sizes
+                    .iter()
+                    .map(|(.., size)| compression_ratio(size.arrow_bytes, size.parquet_bytes))
+                    .collect::<Float64Array>(),
+            ),
+        ];
+
+        Ok(RecordBatch::try_new(self.schema(), columns)?)
+    }
+}
+
+fn parquet_file_columns_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("path", DataType::Utf8, false),
+        Field::new("column_name", DataType::Utf8, false),
+        Field::new("arrow_size_bytes", DataType::UInt64, false),
+        Field::new("parquet_size_bytes", DataType::UInt64, false),
+        Field::new("compression_ratio", DataType::Float64, true),
+    ];
+
+    Arc::new(DatafusionSchema::new(columns))
+}
+
+/// Returns the persisted parquet files of every table in the database, with their table names
+fn table_parquet_files(
+    db_name: &str,
+    catalog: &Catalog,
+    buffer: &dyn ChunkContainer,
+) -> Vec<(String, ParquetFile)> {
+    let Some(db_schema) = catalog.db_schema(db_name) else {
+        return vec![];
+    };
+
+    db_schema
+        .table_names()
+        .into_iter()
+        .flat_map(|table_name| {
+            buffer
+                .parquet_files(db_name, &table_name)
+                .into_iter()
+                .map(move |file| (table_name.clone(), file))
+        })
+        .collect()
+}
+
+/// How many times smaller the persisted data is than the data it was written from, or `None` if
+/// nothing was written
+fn compression_ratio(arrow_bytes: u64, parquet_bytes: u64) -> Option<f64> {
+    (parquet_bytes > 0).then(|| arrow_bytes as f64 / parquet_bytes as f64)
+}
@@ -1,3 +1,4 @@
+use crate::persister::parquet_checksum;
 use crate::persister::serialize_to_parquet;
 use crate::persister::Error;
 use crate::ParquetFile;
@@ -63,6 +64,7 @@ impl ParquetCache {
         let parquet_path =
             path.unwrap_or_else(|| ObjPath::from(format!("{db_name}-{table_name}-{id}")));
         let size_bytes = parquet.bytes.len() as u64;
+        let checksum = parquet_checksum(&parquet.bytes);
         let meta_data = parquet.meta_data;
 
         // Lock the data structure until everything is written into the object
@@ -89,6 +91,9 @@ impl ParquetCache {
                                 row_count: meta_data.num_rows as u64,
                                 min_time,
                                 max_time,
+                                checksum: Some(checksum.clone()),
+                                column_stats: Default::default(),
+                                column_sizes: Default::default(),
                             },
                         );
                     })
@@ -101,6 +106,9 @@ impl ParquetCache {
                                 row_count: meta_data.num_rows as u64,
                                 min_time,
                                 max_time,
+                                checksum: Some(checksum.clone()),
+                                column_stats: Default::default(),
+                                column_sizes: Default::default(),
                             },
                         )])
                     });
@@ -116,6 +124,9 @@ impl ParquetCache {
                             row_count: meta_data.num_rows as u64,
                             min_time,
                             max_time,
+                            checksum: Some(checksum),
+                            column_stats: Default::default(),
+                            column_sizes: Default::default(),
                         },
                     )]),
                 )])
@@ -1,15 +1,18 @@
 //! Implementation of the Catalog that sits entirely in memory.
 
-use crate::SequenceNumber;
+use crate::{Precision, ReplayMode, SequenceNumber};
 use data_types::ColumnType;
+use influxdb_line_protocol::FieldValue;
 use observability_deps::tracing::info;
 use parking_lot::RwLock;
 use schema::{InfluxColumnType, InfluxFieldType, Schema, SchemaBuilder};
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
+use std::num::{NonZeroU64, NonZeroU8};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -34,12 +37,54 @@ pub enum Error {
         Catalog::NUM_DBS_LIMIT
     )]
     TooManyDbs,
+
+    #[error("database not found: {0}")]
+    DatabaseNotFound(String),
+
+    #[error("table {table_name} not found in database {db_name}")]
+    TableNotFound { db_name: String, table_name: String },
+
+    #[error("field {field_name} not found in table {table_name}")]
+    FieldNotFound {
+        table_name: String,
+        field_name: String,
+    },
+
+    #[error("invalid validation rule for field {field_name}: {reason}")]
+    InvalidFieldValidation { field_name: String, reason: String },
+
+    #[error("invalid time validation for database {db_name}: {reason}")]
+    InvalidTimeValidation { db_name: String, reason: String },
+
+    #[error("invalid tag normalization for table {table_name}: {reason}")]
+    InvalidTagNormalization { table_name: String, reason: String },
+
+    #[error("invalid definition for table {table_name}: {reason}")]
+    InvalidTableDefinition { table_name: String, reason: String },
+
+    #[error("invalid load shedding for database {db_name}: {percent}% is more than 100%")]
+    InvalidLoadShedding { db_name: String, percent: u8 },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 pub const TIME_COLUMN_NAME: &str = "time";
 
+/// The tag added to rows that had a field value fail a validation rule with the
+/// [`ValidationAction::TagAsSuspect`] action.
+pub const SUSPECT_TAG_NAME: &str = "_suspect";
+
+/// The column recording the time, in nanoseconds, that the server received a row, for databases
+/// with provenance columns enabled.
+pub const INGESTED_AT_COLUMN_NAME: &str = "_ingested_at";
+
+/// The tag recording where a row was written from, for databases with provenance columns enabled.
+pub const SOURCE_COLUMN_NAME: &str = "_source";
+
+/// The table that lines rejected by validation are written to, for databases that quarantine
+/// rejected writes.
+pub const REJECTED_WRITES_TABLE_NAME: &str = "_rejected_writes";
+
 #[derive(Debug)]
 pub struct Catalog {
     inner: RwLock<InnerCatalog>,
@@ -159,6 +204,295 @@ impl Catalog {
     pub fn list_databases(&self) -> Vec<String> {
         self.inner.read().databases.keys().cloned().collect()
     }
+
+    /// Returns the names of the databases with a retention period, along with the period
+    pub(crate) fn retention_periods(&self) -> Vec<(String, Duration)> {
+        self.inner
+            .read()
+            .databases
+            .values()
+            .filter_map(|db| Some((db.name.clone(), db.retention_period()?)))
+            .collect()
+    }
+
+    /// Applies `update` to a copy of a database's schema, creating the database if it doesn't
+    /// exist yet, and replaces the database with it. `update` returns false if it left the
+    /// schema unchanged. If the catalog was updated concurrently, the update is retried against
+    /// the new schema.
+    fn update_db(
+        &self,
+        db_name: &str,
+        update: impl FnMut(&mut DatabaseSchema) -> Result<bool>,
+    ) -> Result<()> {
+        self.update_db_with(|| self.db_or_create(db_name), update)
+    }
+
+    /// Like [`Catalog::update_db`], but the database must already exist.
+    fn update_existing_db(
+        &self,
+        db_name: &str,
+        update: impl FnMut(&mut DatabaseSchema) -> Result<bool>,
+    ) -> Result<()> {
+        self.update_db_with(
+            || {
+                let inner = self.inner.read();
+                let db = inner
+                    .databases
+                    .get(db_name)
+                    .cloned()
+                    .ok_or_else(|| Error::DatabaseNotFound(db_name.to_string()))?;
+                Ok((inner.sequence, db))
+            },
+            update,
+        )
+    }
+
+    fn update_db_with(
+        &self,
+        mut load: impl FnMut() -> Result<(SequenceNumber, Arc<DatabaseSchema>)>,
+        mut update: impl FnMut(&mut DatabaseSchema) -> Result<bool>,
+    ) -> Result<()> {
+        loop {
+            let (sequence, db) = load()?;
+            let mut db = DatabaseSchema::clone(&db);
+            if !update(&mut db)? {
+                return Ok(());
+            }
+
+            match self.replace_database(sequence, Arc::new(db)) {
+                Err(Error::CatalogUpdatedElsewhere) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Enables or disables the provenance columns for a database, creating the database if it
+    /// doesn't exist yet.
+    pub fn set_provenance_columns(&self, db_name: &str, enabled: bool) -> Result<()> {
+        self.update_db(db_name, |db| {
+            let changed = db.provenance_columns != enabled;
+            db.provenance_columns = enabled;
+            Ok(changed)
+        })
+    }
+
+    /// Sets the limits on timestamps accepted for writes to a database, or removes them if
+    /// `None`, creating the database if it doesn't exist yet.
+    pub fn set_time_validation(
+        &self,
+        db_name: &str,
+        time_validation: Option<TimeValidation>,
+    ) -> Result<()> {
+        if let Some(time_validation) = &time_validation {
+            time_validation
+                .check_valid()
+                .map_err(|reason| Error::InvalidTimeValidation {
+                    db_name: db_name.to_string(),
+                    reason,
+                })?;
+        }
+
+        self.update_db(db_name, |db| {
+            let changed = db.time_validation != time_validation;
+            db.time_validation = time_validation.clone();
+            Ok(changed)
+        })
+    }
+
+    /// Sets the default timeout for queries against a database, or removes it if `None`,
+    /// creating the database if it doesn't exist yet.
+    pub fn set_query_timeout(&self, db_name: &str, timeout_ms: Option<NonZeroU64>) -> Result<()> {
+        self.update_db(db_name, |db| {
+            let changed = db.query_timeout_ms != timeout_ms;
+            db.query_timeout_ms = timeout_ms;
+            Ok(changed)
+        })
+    }
+
+    /// Sets the percentage of lines written to a database that are dropped rather than stored, or
+    /// stops dropping them if `None`, creating the database if it doesn't exist yet.
+    pub fn set_load_shedding(&self, db_name: &str, shed_percent: Option<NonZeroU8>) -> Result<()> {
+        if let Some(percent) = shed_percent.filter(|p| p.get() > 100) {
+            return Err(Error::InvalidLoadShedding {
+                db_name: db_name.to_string(),
+                percent: percent.get(),
+            });
+        }
+
+        self.update_db(db_name, |db| {
+            let changed = db.shed_percent != shed_percent;
+            db.shed_percent = shed_percent;
+            Ok(changed)
+        })
+    }
+
+    /// Sets how long data is kept in a database, or keeps it forever if `None`, creating the
+    /// database if it doesn't exist yet. Queries don't return data older than the retention
+    /// period.
+    pub fn set_retention_period(
+        &self,
+        db_name: &str,
+        retention_period_ms: Option<NonZeroU64>,
+    ) -> Result<()> {
+        self.update_db(db_name, |db| {
+            let changed = db.retention_period_ms != retention_period_ms;
+            db.retention_period_ms = retention_period_ms;
+            Ok(changed)
+        })
+    }
+
+    /// Sets which of a database's writes in the WAL are replayed when the server starts, or
+    /// replays all of them if `None`, creating the database if it doesn't exist yet. A replay
+    /// mode given at startup overrides it.
+    pub fn set_replay_mode(&self, db_name: &str, replay_mode: Option<ReplayMode>) -> Result<()> {
+        self.update_db(db_name, |db| {
+            let changed = db.replay_mode != replay_mode;
+            db.replay_mode = replay_mode;
+            Ok(changed)
+        })
+    }
+
+    /// Enables or disables quarantining of rejected writes for a database, creating the database if
+    /// it doesn't exist yet. Lines rejected by validation are then written to the
+    /// [`REJECTED_WRITES_TABLE_NAME`] table along with the reason they were rejected.
+    pub fn set_quarantine_rejected_writes(&self, db_name: &str, enabled: bool) -> Result<()> {
+        self.update_db(db_name, |db| {
+            let changed = db.quarantine_rejected_writes != enabled;
+            db.quarantine_rejected_writes = enabled;
+            Ok(changed)
+        })
+    }
+
+    /// Enables or disables strict tables for a database, creating the database if it doesn't
+    /// exist yet. Writes to a database with strict tables can't create tables, so they must be
+    /// declared with [`Catalog::create_table`] first.
+    pub fn set_strict_tables(&self, db_name: &str, enabled: bool) -> Result<()> {
+        self.update_db(db_name, |db| {
+            let changed = db.strict_tables != enabled;
+            db.strict_tables = enabled;
+            Ok(changed)
+        })
+    }
+
+    /// Declares a table with the given tags, creating the database if it doesn't exist yet.
+    /// Fields are added by the first writes to the table. Does nothing if the table already
+    /// exists.
+    pub fn create_table(
+        &self,
+        db_name: &str,
+        table_name: &str,
+        tags: &[String],
+        time: i64,
+    ) -> Result<()> {
+        let invalid = |reason: String| Error::InvalidTableDefinition {
+            table_name: table_name.to_string(),
+            reason,
+        };
+        if table_name.is_empty() {
+            return Err(invalid("table name must not be empty".to_string()));
+        }
+        if let Some(tag) = tags
+            .iter()
+            .find(|tag| tag.is_empty() || *tag == TIME_COLUMN_NAME)
+        {
+            return Err(invalid(format!("invalid tag name '{tag}'")));
+        }
+
+        self.update_db(db_name, |db| {
+            if db.table_exists(table_name) {
+                return Ok(false);
+            }
+
+            let columns = tags
+                .iter()
+                .map(|tag| (tag.clone(), ColumnType::Tag as i16))
+                .chain([(TIME_COLUMN_NAME.to_string(), ColumnType::Time as i16)])
+                .collect();
+            let table = TableDefinition::new_logged(
+                table_name,
+                columns,
+                time,
+                SchemaChangeOrigin::Management,
+            );
+            db.tables.insert(table_name.to_string(), table);
+            Ok(true)
+        })
+    }
+
+    /// Sets the validation applied to values written to a field, or removes it if `None`. The
+    /// field must already exist in the table.
+    pub fn set_field_validation(
+        &self,
+        db_name: &str,
+        table_name: &str,
+        field_name: &str,
+        validation: Option<FieldValidation>,
+    ) -> Result<()> {
+        self.update_existing_db(db_name, |db| {
+            let table = db
+                .tables
+                .get_mut(table_name)
+                .ok_or_else(|| Error::TableNotFound {
+                    db_name: db_name.to_string(),
+                    table_name: table_name.to_string(),
+                })?;
+
+            match &validation {
+                Some(validation) => {
+                    let column_type =
+                        table.schema.field_type_by_name(field_name).ok_or_else(|| {
+                            Error::FieldNotFound {
+                                table_name: table_name.to_string(),
+                                field_name: field_name.to_string(),
+                            }
+                        })?;
+                    validation.check_applies_to(column_type).map_err(|reason| {
+                        Error::InvalidFieldValidation {
+                            field_name: field_name.to_string(),
+                            reason,
+                        }
+                    })?;
+                    table
+                        .field_validations
+                        .insert(field_name.to_string(), validation.clone());
+                }
+                None => {
+                    table.field_validations.remove(field_name);
+                }
+            }
+            Ok(true)
+        })
+    }
+
+    /// Sets how tag values written to a table are normalized, or removes the normalization if
+    /// `None`. The table must already exist.
+    pub fn set_tag_normalization(
+        &self,
+        db_name: &str,
+        table_name: &str,
+        normalization: Option<TagNormalization>,
+    ) -> Result<()> {
+        if let Some(normalization) = &normalization {
+            normalization
+                .check_valid()
+                .map_err(|reason| Error::InvalidTagNormalization {
+                    table_name: table_name.to_string(),
+                    reason,
+                })?;
+        }
+
+        self.update_existing_db(db_name, |db| {
+            let table = db
+                .tables
+                .get_mut(table_name)
+                .ok_or_else(|| Error::TableNotFound {
+                    db_name: db_name.to_string(),
+                    table_name: table_name.to_string(),
+                })?;
+            table.tag_normalization = normalization.clone();
+            Ok(true)
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Default)]
@@ -191,6 +525,31 @@ pub struct DatabaseSchema {
     pub name: String,
     /// The database is a map of tables
     pub(crate) tables: BTreeMap<String, TableDefinition>,
+    /// If set, every row written has the `_ingested_at` and `_source` columns added
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) provenance_columns: bool,
+    /// Limits on the timestamps of points written to the database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) time_validation: Option<TimeValidation>,
+    /// Queries against the database that run longer than this are cancelled, unless the
+    /// request sets its own timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) query_timeout_ms: Option<NonZeroU64>,
+    /// If set, writes are rejected for tables that haven't been declared
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) strict_tables: bool,
+    /// The percentage of lines written to the database that are dropped to shed load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) shed_percent: Option<NonZeroU8>,
+    /// If set, lines rejected by validation are written to the `_rejected_writes` table
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) quarantine_rejected_writes: bool,
+    /// Data older than this is no longer returned by queries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) retention_period_ms: Option<NonZeroU64>,
+    /// Which of the database's writes in the WAL are replayed when the server starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) replay_mode: Option<ReplayMode>,
 }
 
 impl DatabaseSchema {
@@ -198,9 +557,51 @@ impl DatabaseSchema {
         Self {
             name: name.into(),
             tables: BTreeMap::new(),
+            provenance_columns: false,
+            time_validation: None,
+            query_timeout_ms: None,
+            strict_tables: false,
+            shed_percent: None,
+            quarantine_rejected_writes: false,
+            retention_period_ms: None,
+            replay_mode: None,
         }
     }
 
+    pub fn provenance_columns(&self) -> bool {
+        self.provenance_columns
+    }
+
+    pub fn time_validation(&self) -> Option<&TimeValidation> {
+        self.time_validation.as_ref()
+    }
+
+    pub fn strict_tables(&self) -> bool {
+        self.strict_tables
+    }
+
+    pub fn shed_percent(&self) -> Option<NonZeroU8> {
+        self.shed_percent
+    }
+
+    pub fn quarantine_rejected_writes(&self) -> bool {
+        self.quarantine_rejected_writes
+    }
+
+    pub fn retention_period(&self) -> Option<Duration> {
+        self.retention_period_ms
+            .map(|retention_ms| Duration::from_millis(retention_ms.get()))
+    }
+
+    pub fn replay_mode(&self) -> Option<ReplayMode> {
+        self.replay_mode
+    }
+
+    pub fn query_timeout(&self) -> Option<Duration> {
+        self.query_timeout_ms
+            .map(|timeout_ms| Duration::from_millis(timeout_ms.get()))
+    }
+
     pub fn get_table_schema(&self, table_name: &str) -> Option<&Schema> {
         self.tables.get(table_name).map(|table| &table.schema)
     }
@@ -224,6 +625,44 @@ pub struct TableDefinition {
     #[serde(skip_serializing, skip_deserializing)]
     pub schema: Schema,
     columns: BTreeMap<String, i16>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    field_validations: BTreeMap<String, FieldValidation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag_normalization: Option<TagNormalization>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    schema_changes: Vec<SchemaChange>,
+}
+
+/// A column added to a table, recorded in the table's schema change log
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct SchemaChange {
+    pub column: String,
+    pub column_type: i16,
+    /// When the column was added, in nanoseconds since the epoch
+    pub time: i64,
+    pub origin: SchemaChangeOrigin,
+}
+
+/// What added a column to a table
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaChangeOrigin {
+    /// The column was in a line written to the table
+    Write,
+    /// The server added the column, such as a provenance column or the suspect tag
+    Server,
+    /// The column was declared through the API when the table was created
+    Management,
+}
+
+impl SchemaChangeOrigin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Write => "write",
+            Self::Server => "server",
+            Self::Management => "management",
+        }
+    }
 }
 
 struct TableDefinitionVisitor;
@@ -241,6 +680,9 @@ impl<'de> Visitor<'de> for TableDefinitionVisitor {
     {
         let mut name = None;
         let mut columns = None;
+        let mut field_validations = None;
+        let mut tag_normalization = None;
+        let mut schema_changes = None;
         while let Some(key) = map.next_key::<String>()? {
             match key.as_str() {
                 "name" => {
@@ -255,6 +697,25 @@ impl<'de> Visitor<'de> for TableDefinitionVisitor {
                     }
                     columns = Some(map.next_value::<BTreeMap<String, i16>>()?);
                 }
+                "field_validations" => {
+                    if field_validations.is_some() {
+                        return Err(serde::de::Error::duplicate_field("field_validations"));
+                    }
+                    field_validations =
+                        Some(map.next_value::<BTreeMap<String, FieldValidation>>()?);
+                }
+                "tag_normalization" => {
+                    if tag_normalization.is_some() {
+                        return Err(serde::de::Error::duplicate_field("tag_normalization"));
+                    }
+                    tag_normalization = Some(map.next_value::<TagNormalization>()?);
+                }
+                "schema_changes" => {
+                    if schema_changes.is_some() {
+                        return Err(serde::de::Error::duplicate_field("schema_changes"));
+                    }
+                    schema_changes = Some(map.next_value::<Vec<SchemaChange>>()?);
+                }
                 _ => {
                     let _ = map.next_value::<serde::de::IgnoredAny>()?;
                 }
@@ -263,7 +724,12 @@ impl<'de> Visitor<'de> for TableDefinitionVisitor {
         let name = name.ok_or_else(|| serde::de::Error::missing_field("name"))?;
         let columns = columns.ok_or_else(|| serde::de::Error::missing_field("columns"))?;
 
-        Ok(TableDefinition::new(name, columns))
+        let mut table = TableDefinition::new(name, columns);
+        table.field_validations = field_validations.unwrap_or_default();
+        table.tag_normalization = tag_normalization;
+        table.schema_changes = schema_changes.unwrap_or_default();
+
+        Ok(table)
     }
 }
 
@@ -291,15 +757,51 @@ impl TableDefinition {
             name: name.into(),
             schema,
             columns,
+            field_validations: BTreeMap::new(),
+            tag_normalization: None,
+            schema_changes: vec![],
         }
     }
 
+    /// Creates a table, logging its columns as the first schema changes
+    pub(crate) fn new_logged(
+        name: impl Into<String>,
+        columns: BTreeMap<String, i16>,
+        time: i64,
+        origin: SchemaChangeOrigin,
+    ) -> Self {
+        let mut table = Self::new(name, columns);
+        table.schema_changes = table
+            .columns
+            .iter()
+            .map(|(column, column_type)| SchemaChange {
+                column: column.clone(),
+                column_type: *column_type,
+                time,
+                origin,
+            })
+            .collect();
+        table
+    }
+
     pub(crate) fn column_exists(&self, column: &str) -> bool {
         self.columns.contains_key(column)
     }
 
-    pub(crate) fn add_columns(&mut self, columns: Vec<(String, i16)>) {
+    /// Adds the columns to the table, logging them as schema changes made at `time`
+    pub(crate) fn add_columns(
+        &mut self,
+        columns: Vec<(String, i16)>,
+        time: i64,
+        origin: SchemaChangeOrigin,
+    ) {
         for (name, column_type) in columns.into_iter() {
+            self.schema_changes.push(SchemaChange {
+                column: name.clone(),
+                column_type,
+                time,
+                origin,
+            });
             self.columns.insert(name, column_type);
         }
 
@@ -328,6 +830,22 @@ impl TableDefinition {
             .collect()
     }
 
+    /// Returns the validation applied to values written to the given field, if any
+    pub fn field_validation(&self, field_name: &str) -> Option<&FieldValidation> {
+        self.field_validations.get(field_name)
+    }
+
+    /// Returns how tag values written to the table are normalized, if at all
+    pub fn tag_normalization(&self) -> Option<&TagNormalization> {
+        self.tag_normalization.as_ref()
+    }
+
+    /// Returns the columns added to the table, oldest first. Tables created before schema
+    /// changes were logged only list the columns added since.
+    pub fn schema_changes(&self) -> &[SchemaChange] {
+        &self.schema_changes
+    }
+
     #[allow(dead_code)]
     pub(crate) fn schema(&self) -> &Schema {
         &self.schema
@@ -339,6 +857,245 @@ impl TableDefinition {
     }
 }
 
+/// Canonicalizes the tag values written to a table, so that values differing only in case or
+/// surrounding whitespace, or known aliases, end up in the same series.
+#[derive(Debug, Default, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct TagNormalization {
+    /// Converts tag values to lowercase
+    #[serde(default)]
+    pub lowercase: bool,
+    /// Removes leading and trailing whitespace from tag values
+    #[serde(default)]
+    pub trim: bool,
+    /// Replaces tag values, after lowercasing and trimming, with the mapped value
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mappings: BTreeMap<String, String>,
+}
+
+impl TagNormalization {
+    /// Checks that every mapping can be matched by a normalized value
+    fn check_valid(&self) -> std::result::Result<(), String> {
+        for from in self.mappings.keys() {
+            if self.lowercase && from.to_lowercase() != *from {
+                return Err(format!(
+                    "mapping from '{from}' can never match as values are lowercased"
+                ));
+            }
+            if self.trim && from.trim() != from {
+                return Err(format!(
+                    "mapping from '{from}' can never match as values are trimmed"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the normalized form of a tag value, or `None` if it normalizes to an empty value.
+    /// Line protocol has no empty tag values, so such a tag is dropped from the line.
+    pub(crate) fn normalize(&self, value: &str) -> Option<String> {
+        let value = if self.trim { value.trim() } else { value };
+        let value = if self.lowercase {
+            value.to_lowercase()
+        } else {
+            value.to_string()
+        };
+        let value = match self.mappings.get(&value) {
+            Some(mapped) => mapped.clone(),
+            None => value,
+        };
+        (!value.is_empty()).then_some(value)
+    }
+}
+
+/// Limits on the timestamps accepted for writes to a database. Points outside of these are
+/// rejected with a per line error.
+#[derive(Debug, Default, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
+pub struct TimeValidation {
+    /// The only timestamp precision accepted, if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<Precision>,
+    /// How far, in seconds, a timestamp may be ahead of the time the write was received
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_future_seconds: Option<u64>,
+    /// The earliest timestamp accepted, in nanoseconds since the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_time: Option<i64>,
+}
+
+impl TimeValidation {
+    fn check_valid(&self) -> std::result::Result<(), String> {
+        if self.precision == Some(Precision::Auto) {
+            return Err(
+                "the accepted precision must be second, millisecond, microsecond or nanosecond"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks a point's timestamp, in nanoseconds, against these limits. `precision` is the
+    /// precision the timestamp was written in, or `None` if the point had no timestamp and was
+    /// given the time the write was received.
+    pub(crate) fn check(
+        &self,
+        time_nanos: i64,
+        precision: Option<Precision>,
+        ingest_time_nanos: i64,
+    ) -> std::result::Result<(), String> {
+        if let (Some(accepted), Some(precision)) = (self.precision, precision) {
+            if accepted != precision {
+                return Err(format!(
+                    "timestamp precision {precision:?} is not accepted, expected {accepted:?}"
+                ));
+            }
+        }
+        if let Some(max_future_seconds) = self.max_future_seconds {
+            let max_future_nanos = i64::try_from(max_future_seconds)
+                .unwrap_or(i64::MAX)
+                .saturating_mul(1_000_000_000);
+            let max_time = ingest_time_nanos.saturating_add(max_future_nanos);
+            if time_nanos > max_time {
+                return Err(format!(
+                    "timestamp {time_nanos} is more than {max_future_seconds} seconds in the future"
+                ));
+            }
+        }
+        if let Some(min_time) = self.min_time {
+            if time_nanos < min_time {
+                return Err(format!(
+                    "timestamp {time_nanos} is before the earliest accepted time {min_time}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A validation applied to values as they are written to a field
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct FieldValidation {
+    pub rule: FieldRule,
+    pub action: ValidationAction,
+}
+
+impl FieldValidation {
+    /// Checks that this validation can be used on a field of the given type
+    fn check_applies_to(&self, column_type: InfluxColumnType) -> std::result::Result<(), String> {
+        match &self.rule {
+            FieldRule::Range { min, max } => {
+                if !matches!(
+                    column_type,
+                    InfluxColumnType::Field(
+                        InfluxFieldType::Float
+                            | InfluxFieldType::Integer
+                            | InfluxFieldType::UInteger
+                    )
+                ) {
+                    return Err("range rules only apply to numeric fields".to_string());
+                }
+                if min.is_some_and(f64::is_nan) || max.is_some_and(f64::is_nan) {
+                    return Err("range bounds must be numbers".to_string());
+                }
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        return Err(format!("range min {min} is greater than max {max}"));
+                    }
+                }
+            }
+            FieldRule::Enum { .. } => {
+                if column_type != InfluxColumnType::Field(InfluxFieldType::String) {
+                    return Err("enum rules only apply to string fields".to_string());
+                }
+                if self.action == ValidationAction::Clamp {
+                    return Err("enum rules can't be used with the clamp action".to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A rule that values written to a field must satisfy
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldRule {
+    /// Numeric values must be within the inclusive bounds
+    Range { min: Option<f64>, max: Option<f64> },
+    /// String values must be one of the allowed values
+    Enum { values: BTreeSet<String> },
+}
+
+// Range bounds are checked to not be NaN when the rule is set
+impl Eq for FieldRule {}
+
+impl FieldRule {
+    /// Returns true if the value satisfies the rule. Values of a type the rule doesn't apply to
+    /// are always allowed.
+    pub(crate) fn allows(&self, value: &FieldValue<'_>) -> bool {
+        match (self, value) {
+            (Self::Range { min, max }, value) => {
+                let v = match value {
+                    FieldValue::F64(v) => *v,
+                    FieldValue::I64(v) => *v as f64,
+                    FieldValue::U64(v) => *v as f64,
+                    _ => return true,
+                };
+                min.map_or(true, |min| v >= min) && max.map_or(true, |max| v <= max)
+            }
+            (Self::Enum { values }, FieldValue::String(v)) => values.contains(v.as_str()),
+            _ => true,
+        }
+    }
+
+    /// Returns the value clamped into the range of the rule. Values that the rule can't clamp
+    /// are returned as is.
+    pub(crate) fn clamp<'a>(&self, value: FieldValue<'a>) -> FieldValue<'a> {
+        let Self::Range { min, max } = self else {
+            return value;
+        };
+        let clamp = |v: f64| {
+            let v = min.map_or(v, |min| v.max(min));
+            max.map_or(v, |max| v.min(max))
+        };
+
+        match value {
+            FieldValue::F64(v) => FieldValue::F64(clamp(v)),
+            FieldValue::I64(v) if !self.allows(&value) => {
+                let v = clamp(v as f64);
+                // round towards the inside of the range so the result still satisfies it
+                FieldValue::I64(if min.is_some_and(|min| v <= min) {
+                    v.ceil() as i64
+                } else {
+                    v.floor() as i64
+                })
+            }
+            FieldValue::U64(v) if !self.allows(&value) => {
+                let v = clamp(v as f64);
+                FieldValue::U64(if min.is_some_and(|min| v <= min) {
+                    v.ceil() as u64
+                } else {
+                    v.floor() as u64
+                })
+            }
+            value => value,
+        }
+    }
+}
+
+/// What to do with a value that fails its field's validation rule
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationAction {
+    /// Reject the line containing the value
+    #[default]
+    Reject,
+    /// Clamp the value into the allowed range
+    Clamp,
+    /// Accept the value, but tag the row with the [`SUSPECT_TAG_NAME`] tag
+    TagAsSuspect,
+}
+
 fn column_type_to_influx_column_type(column_type: &ColumnType) -> InfluxColumnType {
     match column_type {
         ColumnType::I64 => InfluxColumnType::Field(InfluxFieldType::Integer),
@@ -361,6 +1118,14 @@ mod tests {
         let mut database = DatabaseSchema {
             name: "test".to_string(),
             tables: BTreeMap::new(),
+            provenance_columns: false,
+            time_validation: None,
+            query_timeout_ms: None,
+            strict_tables: false,
+            shed_percent: None,
+            quarantine_rejected_writes: false,
+            retention_period_ms: None,
+            replay_mode: None,
         };
         database.tables.insert(
             "test".into(),
@@ -386,6 +1151,14 @@ mod tests {
         let mut database = DatabaseSchema {
             name: "test".to_string(),
             tables: BTreeMap::new(),
+            provenance_columns: false,
+            time_validation: None,
+            query_timeout_ms: None,
+            strict_tables: false,
+            shed_percent: None,
+            quarantine_rejected_writes: false,
+            retention_period_ms: None,
+            replay_mode: None,
         };
         database.tables.insert(
             "test".into(),
@@ -396,12 +1169,334 @@ mod tests {
         );
 
         let table = database.tables.get_mut("test").unwrap();
-        table.add_columns(vec![("test2".to_string(), ColumnType::Tag as i16)]);
+        table.add_columns(
+            vec![("test2".to_string(), ColumnType::Tag as i16)],
+            10,
+            SchemaChangeOrigin::Write,
+        );
         let schema = table.schema();
         assert_eq!(
             schema.field(0).0,
             InfluxColumnType::Field(InfluxFieldType::String)
         );
         assert_eq!(schema.field(1).0, InfluxColumnType::Tag);
+        assert_eq!(
+            table.schema_changes(),
+            &[SchemaChange {
+                column: "test2".to_string(),
+                column_type: ColumnType::Tag as i16,
+                time: 10,
+                origin: SchemaChangeOrigin::Write,
+            }]
+        );
+    }
+
+    #[test]
+    fn set_field_validation() {
+        let catalog = Catalog::new();
+        let mut database = DatabaseSchema::new("test");
+        database.tables.insert(
+            "cpu".into(),
+            TableDefinition::new(
+                "cpu",
+                BTreeMap::from([
+                    ("usage".to_string(), ColumnType::F64 as i16),
+                    ("state".to_string(), ColumnType::String as i16),
+                ]),
+            ),
+        );
+        catalog
+            .replace_database(SequenceNumber::new(0), Arc::new(database))
+            .unwrap();
+
+        let range = FieldValidation {
+            rule: FieldRule::Range {
+                min: Some(0.0),
+                max: Some(100.0),
+            },
+            action: ValidationAction::Clamp,
+        };
+        catalog
+            .set_field_validation("test", "cpu", "usage", Some(range.clone()))
+            .unwrap();
+
+        // a range can't apply to a string field
+        assert!(matches!(
+            catalog.set_field_validation("test", "cpu", "state", Some(range.clone())),
+            Err(Error::InvalidFieldValidation { .. })
+        ));
+        assert!(matches!(
+            catalog.set_field_validation("test", "cpu", "missing", Some(range.clone())),
+            Err(Error::FieldNotFound { .. })
+        ));
+
+        let inner = catalog.clone_inner();
+        let serialized = serde_json::to_string(&inner).unwrap();
+        let deserialized: InnerCatalog = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(inner, deserialized);
+
+        let db = catalog.db_schema("test").unwrap();
+        assert_eq!(
+            db.get_table("cpu").unwrap().field_validation("usage"),
+            Some(&range)
+        );
+
+        catalog
+            .set_field_validation("test", "cpu", "usage", None)
+            .unwrap();
+        let db = catalog.db_schema("test").unwrap();
+        assert_eq!(db.get_table("cpu").unwrap().field_validation("usage"), None);
+    }
+
+    #[test]
+    fn clamp_keeps_integers_in_range() {
+        let rule = FieldRule::Range {
+            min: Some(0.5),
+            max: Some(10.5),
+        };
+        assert_eq!(rule.clamp(FieldValue::I64(-3)), FieldValue::I64(1));
+        assert_eq!(rule.clamp(FieldValue::I64(20)), FieldValue::I64(10));
+        assert_eq!(rule.clamp(FieldValue::I64(5)), FieldValue::I64(5));
+        assert_eq!(rule.clamp(FieldValue::F64(20.0)), FieldValue::F64(10.5));
+        assert_eq!(rule.clamp(FieldValue::U64(0)), FieldValue::U64(1));
+    }
+
+    #[test]
+    fn set_time_validation() {
+        let catalog = Catalog::new();
+        let time_validation = TimeValidation {
+            precision: Some(Precision::Second),
+            max_future_seconds: Some(3600),
+            min_time: Some(946_684_800_000_000_000),
+        };
+        catalog
+            .set_time_validation("test", Some(time_validation))
+            .unwrap();
+        assert_eq!(
+            catalog.db_schema("test").unwrap().time_validation(),
+            Some(&time_validation)
+        );
+
+        let now = 1_700_000_000_000_000_000;
+        assert!(time_validation
+            .check(now, Some(Precision::Second), now)
+            .is_ok());
+        // no timestamp on the line, so the precision doesn't apply
+        assert!(time_validation.check(now, None, now).is_ok());
+        assert!(time_validation
+            .check(now, Some(Precision::Nanosecond), now)
+            .is_err());
+        assert!(time_validation
+            .check(now + 3_601_000_000_000, Some(Precision::Second), now)
+            .is_err());
+        assert!(time_validation
+            .check(0, Some(Precision::Second), now)
+            .is_err());
+
+        let err = catalog
+            .set_time_validation(
+                "test",
+                Some(TimeValidation {
+                    precision: Some(Precision::Auto),
+                    ..Default::default()
+                }),
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidTimeValidation { .. }));
+
+        catalog.set_time_validation("test", None).unwrap();
+        assert_eq!(catalog.db_schema("test").unwrap().time_validation(), None);
+    }
+
+    #[test]
+    fn set_query_timeout() {
+        let catalog = Catalog::new();
+        catalog
+            .set_query_timeout("test", NonZeroU64::new(1500))
+            .unwrap();
+        assert_eq!(
+            catalog.db_schema("test").unwrap().query_timeout(),
+            Some(Duration::from_millis(1500))
+        );
+
+        // the setting survives a round trip through the persisted catalog
+        let json = serde_json::to_string(&catalog.clone_inner()).unwrap();
+        let inner: InnerCatalog = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            Catalog::from_inner(inner)
+                .db_schema("test")
+                .unwrap()
+                .query_timeout(),
+            Some(Duration::from_millis(1500))
+        );
+
+        catalog.set_query_timeout("test", None).unwrap();
+        assert_eq!(catalog.db_schema("test").unwrap().query_timeout(), None);
+    }
+
+    #[test]
+    fn set_retention_period() {
+        let catalog = Catalog::new();
+        catalog
+            .set_retention_period("test", NonZeroU64::new(86_400_000))
+            .unwrap();
+
+        // the setting survives a round trip through the persisted catalog
+        let json = serde_json::to_string(&catalog.clone_inner()).unwrap();
+        let inner: InnerCatalog = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            Catalog::from_inner(inner)
+                .db_schema("test")
+                .unwrap()
+                .retention_period(),
+            Some(Duration::from_secs(86_400))
+        );
+
+        catalog.set_retention_period("test", None).unwrap();
+        assert_eq!(catalog.db_schema("test").unwrap().retention_period(), None);
+    }
+
+    #[test]
+    fn set_replay_mode() {
+        let catalog = Catalog::new();
+        let replay_mode = ReplayMode::Since(Duration::from_secs(30 * 60));
+        catalog.set_replay_mode("test", Some(replay_mode)).unwrap();
+
+        // the setting survives a round trip through the persisted catalog
+        let json = serde_json::to_string(&catalog.clone_inner()).unwrap();
+        assert!(json.contains(r#""replay_mode":"30m""#));
+        let inner: InnerCatalog = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            Catalog::from_inner(inner)
+                .db_schema("test")
+                .unwrap()
+                .replay_mode(),
+            Some(replay_mode)
+        );
+
+        catalog.set_replay_mode("test", None).unwrap();
+        assert_eq!(catalog.db_schema("test").unwrap().replay_mode(), None);
+    }
+
+    #[test]
+    fn create_table() {
+        let catalog = Catalog::new();
+        let err = catalog
+            .create_table("test", "cpu", &["time".to_string()], 0)
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidTableDefinition { .. }));
+
+        catalog
+            .create_table("test", "cpu", &["host".to_string()], 10)
+            .unwrap();
+        catalog.set_strict_tables("test", true).unwrap();
+
+        // declaring the table again leaves it as it was
+        catalog
+            .create_table("test", "cpu", &["region".to_string()], 20)
+            .unwrap();
+
+        // the table and the setting survive a round trip through the persisted catalog
+        let json = serde_json::to_string(&catalog.clone_inner()).unwrap();
+        let inner: InnerCatalog = serde_json::from_str(&json).unwrap();
+        let db = Catalog::from_inner(inner).db_schema("test").unwrap();
+        assert!(db.strict_tables());
+        let table = db.get_table("cpu").unwrap();
+        assert_eq!(
+            table.columns(),
+            &BTreeMap::from([
+                ("host".to_string(), ColumnType::Tag as i16),
+                ("time".to_string(), ColumnType::Time as i16),
+            ])
+        );
+        assert!(table
+            .schema_changes()
+            .iter()
+            .all(|c| c.time == 10 && c.origin == SchemaChangeOrigin::Management));
+    }
+
+    #[test]
+    fn concurrent_updates_are_retried() {
+        let catalog = Catalog::new();
+        catalog.set_strict_tables("test", true).unwrap();
+
+        // updates racing on the same database are retried rather than failing because the
+        // catalog was updated elsewhere
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let catalog = &catalog;
+                s.spawn(move || {
+                    for j in 0..10 {
+                        catalog
+                            .create_table("test", &format!("t{i}_{j}"), &[], 0)
+                            .unwrap();
+                    }
+                });
+            }
+            s.spawn(|| {
+                for _ in 0..10 {
+                    catalog.set_provenance_columns("test", true).unwrap();
+                    catalog.set_provenance_columns("test", false).unwrap();
+                }
+            });
+        });
+
+        let db = catalog.db_schema("test").unwrap();
+        assert_eq!(db.tables.len(), 80);
+        assert!(db.strict_tables());
+        assert!(!db.provenance_columns());
+    }
+
+    #[test]
+    fn set_tag_normalization() {
+        let catalog = Catalog::new();
+        let mut database = DatabaseSchema::new("test");
+        database.tables.insert(
+            "cpu".into(),
+            TableDefinition::new(
+                "cpu",
+                BTreeMap::from([("host".to_string(), ColumnType::Tag as i16)]),
+            ),
+        );
+        catalog
+            .replace_database(SequenceNumber::new(0), Arc::new(database))
+            .unwrap();
+
+        let err = catalog
+            .set_tag_normalization("test", "mem", Some(TagNormalization::default()))
+            .unwrap_err();
+        assert!(matches!(err, Error::TableNotFound { .. }));
+
+        // mappings are matched against the normalized value
+        let err = catalog
+            .set_tag_normalization(
+                "test",
+                "cpu",
+                Some(TagNormalization {
+                    lowercase: true,
+                    trim: false,
+                    mappings: [("Web-1".to_string(), "web01".to_string())].into(),
+                }),
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidTagNormalization { .. }));
+
+        let normalization = TagNormalization {
+            lowercase: true,
+            trim: true,
+            mappings: [("web-1".to_string(), "web01".to_string())].into(),
+        };
+        catalog
+            .set_tag_normalization("test", "cpu", Some(normalization.clone()))
+            .unwrap();
+        assert_eq!(normalization.normalize(" Web-1 ").as_deref(), Some("web01"));
+        assert_eq!(normalization.normalize("HOST").as_deref(), Some("host"));
+        assert_eq!(normalization.normalize("  "), None);
+
+        // the normalization survives a round trip through serialization
+        let json = serde_json::to_string(&catalog.clone_inner()).unwrap();
+        let inner: InnerCatalog = serde_json::from_str(&json).unwrap();
+        let table = inner.databases["test"].get_table("cpu").unwrap();
+        assert_eq!(table.tag_normalization(), Some(&normalization));
     }
 }
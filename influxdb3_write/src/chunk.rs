@@ -16,6 +16,9 @@ pub struct BufferChunk {
     pub(crate) stats: Arc<ChunkStatistics>,
     pub(crate) partition_id: data_types::partition::TransitionPartitionId,
     pub(crate) sort_key: Option<SortKey>,
+    /// False when the buffer can prove that no two rows share a primary key, which lets
+    /// the query planner skip the dedup sort for this chunk
+    pub(crate) may_contain_pk_duplicates: bool,
     pub(crate) id: data_types::ChunkId,
     pub(crate) chunk_order: data_types::ChunkOrder,
 }
@@ -42,7 +45,7 @@ impl QueryChunk for BufferChunk {
     }
 
     fn may_contain_pk_duplicates(&self) -> bool {
-        true
+        self.may_contain_pk_duplicates
     }
 
     fn data(&self) -> QueryChunkData {
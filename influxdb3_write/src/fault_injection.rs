@@ -0,0 +1,196 @@
+//! An object store that injects latency and failures into requests, for testing how failures
+//! of object storage are handled. Available to other crates with the `test-helpers` feature.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use object_store::memory::InMemory;
+use object_store::path::Path;
+use object_store::{
+    GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore, PutOptions, PutResult,
+};
+use parking_lot::Mutex;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use tokio::io::AsyncWrite;
+
+/// A fault to inject into requests against a [`FaultInjectingStore`].
+#[derive(Debug, Clone, Default)]
+pub struct Fault {
+    /// Delay every matching request by this long before it runs
+    pub latency: Option<Duration>,
+    /// Fail every nth matching request, counting from the first. 1 fails every request
+    pub fail_every: Option<usize>,
+    /// Failed puts still write this many leading bytes of the object, leaving a
+    /// truncated file behind
+    pub partial_write_bytes: Option<usize>,
+}
+
+#[derive(Debug)]
+struct FaultRule {
+    prefix: String,
+    fault: Fault,
+    requests: usize,
+}
+
+/// An in memory object store that injects latency and failures into requests for paths
+/// starting with a configured prefix. Faults are deterministic so that tests of failure
+/// handling are repeatable.
+#[derive(Debug, Default)]
+pub struct FaultInjectingStore {
+    inner: InMemory,
+    rules: Mutex<Vec<FaultRule>>,
+}
+
+impl FaultInjectingStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Injects the fault into requests for paths starting with the prefix. The first
+    /// matching rule applies to a request.
+    pub fn inject(&self, prefix: impl Into<String>, fault: Fault) {
+        self.rules.lock().push(FaultRule {
+            prefix: prefix.into(),
+            fault,
+            requests: 0,
+        });
+    }
+
+    /// Removes all injected faults.
+    pub fn clear(&self) {
+        self.rules.lock().clear();
+    }
+
+    /// Counts the request against the matching rule, returning its fault and whether this
+    /// request should fail.
+    fn next_fault(&self, location: &Path) -> Option<(Fault, bool)> {
+        let mut rules = self.rules.lock();
+        let rule = rules
+            .iter_mut()
+            .find(|rule| location.as_ref().starts_with(&rule.prefix))?;
+        rule.requests += 1;
+        let fail = rule
+            .fault
+            .fail_every
+            .is_some_and(|n| rule.requests % n.max(1) == 0);
+        Some((rule.fault.clone(), fail))
+    }
+
+    async fn apply(&self, location: &Path) -> object_store::Result<()> {
+        let Some((fault, fail)) = self.next_fault(location) else {
+            return Ok(());
+        };
+        if let Some(latency) = fault.latency {
+            tokio::time::sleep(latency).await;
+        }
+        if fail {
+            return Err(injected_error(location));
+        }
+        Ok(())
+    }
+}
+
+fn injected_error(location: &Path) -> object_store::Error {
+    object_store::Error::Generic {
+        store: "FaultInjectingStore",
+        source: format!("injected fault for {location}").into(),
+    }
+}
+
+impl Display for FaultInjectingStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FaultInjectingStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for FaultInjectingStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        let Some((fault, fail)) = self.next_fault(location) else {
+            return self.inner.put_opts(location, bytes, opts).await;
+        };
+        if let Some(latency) = fault.latency {
+            tokio::time::sleep(latency).await;
+        }
+        if fail {
+            if let Some(len) = fault.partial_write_bytes {
+                let partial = bytes.slice(..len.min(bytes.len()));
+                self.inner.put_opts(location, partial, opts).await?;
+            }
+            return Err(injected_error(location));
+        }
+        self.inner.put_opts(location, bytes, opts).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.apply(location).await?;
+        self.inner.put_multipart(location).await
+    }
+
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.apply(location).await?;
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        self.apply(location).await?;
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.apply(location).await?;
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.apply(location).await?;
+        self.inner.delete(location).await
+    }
+
+    // listings are matched against rules by their prefix, a listing without one only matching
+    // a rule for the empty prefix
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        let prefix = prefix.cloned();
+        stream::once(async move {
+            match self.apply(&prefix.clone().unwrap_or_default()).await {
+                Ok(()) => self.inner.list(prefix.as_ref()),
+                Err(e) => stream::once(async { Err(e) }).boxed(),
+            }
+        })
+        .flatten()
+        .boxed()
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.apply(&prefix.cloned().unwrap_or_default()).await?;
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.apply(to).await?;
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.apply(to).await?;
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
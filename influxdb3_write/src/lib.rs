@@ -9,6 +9,8 @@
 pub mod cache;
 pub mod catalog;
 mod chunk;
+#[cfg(any(test, feature = "test-helpers"))]
+pub mod fault_injection;
 pub mod paths;
 pub mod persister;
 pub mod wal;
@@ -18,17 +20,19 @@ use crate::paths::{ParquetFilePath, SegmentWalFilePath};
 use async_trait::async_trait;
 use bytes::Bytes;
 use data_types::{NamespaceName, TimestampMinMax};
+use datafusion::common::ScalarValue;
 use datafusion::datasource::object_store::ObjectStoreUrl;
 use datafusion::error::DataFusionError;
 use datafusion::execution::context::SessionState;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use datafusion::prelude::Expr;
+use iox_query::chunk_statistics::{ColumnRange, ColumnRanges};
 use iox_query::QueryChunk;
 use iox_time::Time;
 use parquet::format::FileMetaData;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::ops::Add;
 use std::path::PathBuf;
@@ -54,6 +58,15 @@ pub enum Error {
     #[error("invalid segment duration {0}. Must be one of 1m, 5m, 10m, 15m, 30m, 1h, 2h, 4h")]
     InvalidSegmentDuration(String),
 
+    #[error("invalid replay mode {0}. Must be full, skip, or a duration such as 30m")]
+    InvalidReplayMode(String),
+
+    #[error(
+        "invalid parquet layout {0}. Must be segment, hive, or a template that includes {{table}} \
+         and only the {{db}}, {{table}}, and {{date}} placeholders, such as {{table}}/date={{date}}"
+    )]
+    InvalidParquetLayout(String),
+
     #[error("wal error: {0}")]
     Wal(#[from] wal::Error),
 }
@@ -74,7 +87,7 @@ pub trait Bufferer: Debug + Send + Sync + 'static {
     /// Validates the line protocol, writes it into the WAL if configured, writes it into the in memory buffer
     /// and returns the result with any lines that had errors and summary statistics. This writes into the currently
     /// open segment or it will open one. The open segment id and the memory usage of the currently open segment are
-    /// returned.
+    /// returned. The `source` identifies the writer for databases that record provenance columns.
     async fn write_lp(
         &self,
         database: NamespaceName<'static>,
@@ -82,6 +95,7 @@ pub trait Bufferer: Debug + Send + Sync + 'static {
         ingest_time: Time,
         accept_partial: bool,
         precision: Precision,
+        source: Option<&str>,
     ) -> write_buffer::Result<BufferedWriteRequest>;
 
     /// Returns the configured WAL, if there is one.
@@ -89,6 +103,10 @@ pub trait Bufferer: Debug + Send + Sync + 'static {
 
     /// Returns the catalog
     fn catalog(&self) -> Arc<catalog::Catalog>;
+
+    /// Persists the catalog, so that changes made directly to it, rather than by writes, survive
+    /// a restart.
+    async fn persist_catalog(&self) -> write_buffer::Result<()>;
 }
 
 /// A segment in the buffer that corresponds to a single WAL segment file. It contains a catalog with any updates
@@ -118,6 +136,14 @@ pub trait ChunkContainer: Debug + Send + Sync + 'static {
         projection: Option<&Vec<usize>>,
         ctx: &SessionState,
     ) -> Result<Vec<Arc<dyn QueryChunk>>, DataFusionError>;
+
+    /// Returns the filter that leaves out the rows older than the database's retention period,
+    /// if it has one. Scans add it to their filters once, so that the chunks for them are pruned
+    /// by the same cutoff that is applied to the scanned rows.
+    fn retention_filter(&self, database_name: &str) -> Option<Expr>;
+
+    /// Returns the parquet files persisted for a table
+    fn parquet_files(&self, database_name: &str, table_name: &str) -> Vec<ParquetFile>;
 }
 
 /// The segment identifier, which will be monotonically increasing.
@@ -187,6 +213,121 @@ impl FromStr for SegmentDuration {
     }
 }
 
+/// Which WAL segments that haven't been persisted are replayed into the buffer when the server
+/// starts, set for a database in the catalog or for every database at startup. Writes that
+/// aren't replayed are lost. Serialized the same way it's parsed, as `full`, `skip`, or a
+/// duration such as `30m`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ReplayMode {
+    /// Replay every segment
+    #[default]
+    Full,
+    /// Replay no segments
+    Skip,
+    /// Replay only segments whose time range ends less than this long before the server started
+    Since(Duration),
+}
+
+impl FromStr for ReplayMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Self::Full),
+            "skip" => Ok(Self::Skip),
+            _ => humantime::parse_duration(s)
+                .map(Self::Since)
+                .map_err(|_| Error::InvalidReplayMode(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for ReplayMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::Skip => write!(f, "skip"),
+            Self::Since(since) => write!(f, "{}", humantime::format_duration(*since)),
+        }
+    }
+}
+
+impl TryFrom<String> for ReplayMode {
+    type Error = Error;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ReplayMode> for String {
+    fn from(replay_mode: ReplayMode) -> Self {
+        replay_mode.to_string()
+    }
+}
+
+impl ReplayMode {
+    /// Returns whether a segment covering `range` is replayed by a server started at
+    /// `server_load_time`.
+    pub fn replays(&self, range: SegmentRange, server_load_time: Time) -> bool {
+        match self {
+            Self::Full => true,
+            Self::Skip => false,
+            Self::Since(since) => server_load_time
+                .checked_sub(*since)
+                .map_or(true, |cutoff| range.end_time > cutoff),
+        }
+    }
+}
+
+/// The template of the `hive` parquet layout
+pub const HIVE_PARQUET_LAYOUT: &str = "{table}/date={date}";
+
+/// How the paths of persisted parquet files are laid out in object storage, under `dbs/<db>/`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub enum ParquetLayout {
+    /// A directory per table and segment, named by the segment's start time, such as
+    /// `cpu/2024-01-30T10-00`
+    #[default]
+    Segment,
+    /// Directories built from a template with `{db}`, `{table}`, and `{date}` placeholders, such
+    /// as the Hive style `{table}/date={date}` that external query engines can use to prune
+    /// files. `{date}` is the day the file's segment starts on, as `YYYY-MM-DD`.
+    Template(String),
+}
+
+impl FromStr for ParquetLayout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "segment" => Ok(Self::Segment),
+            "hive" => Ok(Self::Template(HIVE_PARQUET_LAYOUT.to_string())),
+            // the files of different tables persisted from the same segment have the same name,
+            // so each table needs a directory of its own
+            template if template.contains("{table}") && valid_placeholders(template) => {
+                Ok(Self::Template(template.to_string()))
+            }
+            _ => Err(Error::InvalidParquetLayout(s.to_string())),
+        }
+    }
+}
+
+/// Whether the braces in a parquet layout template only enclose known placeholders
+fn valid_placeholders(template: &str) -> bool {
+    let mut parts = template.split('{');
+    let before_first = parts.next().unwrap_or_default();
+
+    !before_first.contains('}')
+        && parts.all(|part| {
+            matches!(
+                part.split_once('}'),
+                Some(("db" | "table" | "date", rest)) if !rest.contains('}')
+            )
+        })
+}
+
 impl SegmentRange {
     /// Given the time will find the appropriate start and end time for the given duration.
     pub fn from_time_and_duration(
@@ -322,7 +463,9 @@ pub trait Persister: Debug + Send + Sync + 'static {
     async fn load_parquet_file(&self, path: ParquetFilePath) -> Result<Bytes, Self::Error>;
 
     /// Persists the catalog with the given segment ID. If this is the highest segment ID, it will
-    /// be the catalog that is returned the next time `load_catalog` is called.
+    /// be the catalog that is returned the next time `load_catalog` is called. If a catalog with a
+    /// higher segment ID has already been persisted, the catalog is written over that one instead,
+    /// unless it is older than it.
     async fn persist_catalog(
         &self,
         segment_id: SegmentId,
@@ -336,13 +479,28 @@ pub trait Persister: Debug + Send + Sync + 'static {
         persisted_segment: &PersistedSegment,
     ) -> Result<(), Self::Error>;
 
+    /// Writes the WAL entries of a segment that could not be replayed into the buffer to object
+    /// storage, so that they can be inspected and written again.
+    async fn persist_quarantined_writes(
+        &self,
+        segment_id: SegmentId,
+        writes: &[QuarantinedWrite],
+    ) -> Result<(), Self::Error>;
+
     // Writes a SendableRecorgBatchStream to the Parquet format and persists it
-    // to Object Store at the given path. Returns the number of bytes written and the file metadata.
+    // to Object Store at the given path. Returns the number of bytes written, the file metadata
+    // and the checksum of the written bytes.
     async fn persist_parquet_file(
         &self,
         path: ParquetFilePath,
         record_batch: SendableRecordBatchStream,
-    ) -> Result<(u64, FileMetaData), Self::Error>;
+    ) -> Result<(u64, FileMetaData, String), Self::Error>;
+
+    /// Deletes the file holding the parquet file list of the given segment from object storage.
+    async fn delete_segment(&self, segment_id: SegmentId) -> Result<(), Self::Error>;
+
+    /// Deletes a persisted parquet file from object storage.
+    async fn delete_parquet_file(&self, path: ParquetFilePath) -> Result<(), Self::Error>;
 
     /// Returns the configured `ObjectStore` that data is loaded from and persisted to.
     fn object_store(&self) -> Arc<dyn object_store::ObjectStore>;
@@ -355,6 +513,11 @@ pub trait Persister: Debug + Send + Sync + 'static {
         ObjectStoreUrl::parse(DEFAULT_OBJECT_STORE_URL).unwrap()
     }
 
+    /// The layout that paths of persisted parquet files are created with.
+    fn parquet_layout(&self) -> ParquetLayout {
+        ParquetLayout::Segment
+    }
+
     fn as_any(&self) -> &dyn Any;
 }
 
@@ -434,6 +597,10 @@ pub struct LpWriteOp {
     pub lp: String,
     pub default_time: i64,
     pub precision: Precision,
+    /// Where the write came from, recorded in the `_source` column of databases that have
+    /// provenance columns enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 /// A single write request can have many lines in it. A writer can request to accept all lines that are valid, while
@@ -454,6 +621,8 @@ pub struct BufferedWriteRequest {
     pub line_count: usize,
     pub field_count: usize,
     pub tag_count: usize,
+    /// Lines dropped without being validated because the database is shedding load
+    pub shed_lines: usize,
 }
 
 /// A persisted Catalog that contains the database, table, and column schemas.
@@ -463,6 +632,19 @@ pub struct PersistedCatalog {
     pub segment_id: SegmentId,
     /// The catalog that was persisted.
     pub catalog: catalog::InnerCatalog,
+    /// The fencing epoch held by the server that persisted the catalog, or 0 if it held none.
+    pub fencing_epoch: u64,
+}
+
+/// A write from a WAL segment that failed validation when the segment was replayed, for example
+/// because the schema changed after it was written.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct QuarantinedWrite {
+    pub db_name: String,
+    /// The line protocol that was rejected
+    pub lp: String,
+    /// Why the line protocol was rejected
+    pub error: String,
 }
 
 /// The collection of Parquet files that were persisted for a segment.
@@ -483,6 +665,14 @@ pub struct PersistedSegment {
     /// The collection of databases that had tables persisted in this segment. The tables will then have their
     /// name and the parquet files.
     pub databases: HashMap<String, DatabaseTables>,
+    /// True if this segment holds late arriving data for a time range that an earlier segment
+    /// already persisted. Such segments are merged into the earlier one by the persister.
+    #[serde(default)]
+    pub late_data: bool,
+    /// The fencing epoch held by the server that persisted the segment, or 0 if it held none.
+    /// Set by the persister when the segment is written.
+    #[serde(default)]
+    pub fencing_epoch: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Eq, PartialEq, Clone)]
@@ -509,6 +699,28 @@ pub struct ParquetFile {
     pub row_count: u64,
     pub min_time: i64,
     pub max_time: i64,
+    /// Hex encoded SHA-256 of the file contents, checked when the file is loaded back. Files
+    /// persisted before checksums were recorded don't have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// Statistics for the non-time columns in the file, used to prune the file from queries
+    /// without reading its footer from object storage.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_stats: BTreeMap<String, ColumnStats>,
+    /// The space each column takes in the file and took in memory before it was written. Files
+    /// persisted before sizes were recorded don't have any.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_sizes: BTreeMap<String, ColumnSize>,
+}
+
+/// The size of a column in a persisted parquet file, compared to the Arrow data it was written
+/// from
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Default)]
+pub struct ColumnSize {
+    /// Bytes used by the column's Arrow arrays when the file was written
+    pub arrow_bytes: u64,
+    /// Compressed bytes of the column's chunks across all of the file's row groups
+    pub parquet_bytes: u64,
 }
 
 impl ParquetFile {
@@ -518,8 +730,88 @@ impl ParquetFile {
             max: self.max_time,
         }
     }
+
+    /// The value ranges of the columns in this file that have both a min and a max recorded.
+    pub fn column_ranges(&self) -> ColumnRanges {
+        let ranges = self
+            .column_stats
+            .iter()
+            .filter_map(|(name, stats)| {
+                let (Some(min), Some(max)) = (&stats.min, &stats.max) else {
+                    return None;
+                };
+                let range = ColumnRange {
+                    min_value: Arc::new(min.to_scalar()),
+                    max_value: Arc::new(max.to_scalar()),
+                };
+                Some((Arc::from(name.as_str()), range))
+            })
+            .collect();
+
+        Arc::new(ranges)
+    }
+}
+
+/// The statistics for a single column in a persisted parquet file. The min and max are `None`
+/// when the column only contains nulls.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct ColumnStats {
+    pub min: Option<StatValue>,
+    pub max: Option<StatValue>,
+    pub null_count: u64,
+}
+
+/// A min or max value recorded in [`ColumnStats`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum StatValue {
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl StatValue {
+    /// Converts a scalar from a column into a stat value. Tag values come through as strings.
+    pub fn from_scalar(scalar: &ScalarValue) -> Option<Self> {
+        match scalar {
+            ScalarValue::Int64(Some(v)) => Some(Self::I64(*v)),
+            ScalarValue::UInt64(Some(v)) => Some(Self::U64(*v)),
+            ScalarValue::Float64(Some(v)) => Some(Self::F64(*v)),
+            ScalarValue::Boolean(Some(v)) => Some(Self::Bool(*v)),
+            ScalarValue::Utf8(Some(v)) => Some(Self::String(v.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_scalar(&self) -> ScalarValue {
+        match self {
+            Self::I64(v) => ScalarValue::Int64(Some(*v)),
+            Self::U64(v) => ScalarValue::UInt64(Some(*v)),
+            Self::F64(v) => ScalarValue::Float64(Some(*v)),
+            Self::Bool(v) => ScalarValue::Boolean(Some(*v)),
+            Self::String(v) => ScalarValue::Utf8(Some(v.clone())),
+        }
+    }
 }
 
+// floats are compared by their bits so that stats can be compared for equality
+impl PartialEq for StatValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::I64(a), Self::I64(b)) => a == b,
+            (Self::U64(a), Self::U64(b)) => a == b,
+            (Self::F64(a), Self::F64(b)) => a.to_bits() == b.to_bits(),
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for StatValue {}
+
 /// The precision of the timestamp
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -605,7 +897,10 @@ mod test_helpers {
             Time::from_timestamp_nanos(0),
             SegmentDuration::new_5m(),
             false,
+            true,
             Precision::Nanosecond,
+            None,
+            None,
             seq,
         )
         .unwrap();
@@ -635,7 +930,10 @@ mod test_helpers {
             Time::from_timestamp_nanos(default_time),
             SegmentDuration::new_5m(),
             false,
+            true,
             Precision::Nanosecond,
+            None,
+            None,
             SequenceNumber::new(0),
         )
         .unwrap();
@@ -652,6 +950,28 @@ mod test_helpers {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_parquet_layout() {
+        assert_eq!(
+            ParquetLayout::from_str("segment").unwrap(),
+            ParquetLayout::Segment
+        );
+        assert_eq!(
+            ParquetLayout::from_str("hive").unwrap(),
+            ParquetLayout::Template("{table}/date={date}".to_string())
+        );
+        assert_eq!(
+            ParquetLayout::from_str("{db}/{table}/day={date}").unwrap(),
+            ParquetLayout::Template("{db}/{table}/day={date}".to_string())
+        );
+
+        // every table needs a directory of its own
+        assert!(ParquetLayout::from_str("date={date}").is_err());
+        assert!(ParquetLayout::from_str("{table}/hour={hour}").is_err());
+        assert!(ParquetLayout::from_str("{table}/{date").is_err());
+        assert!(ParquetLayout::from_str("{table}}/{date}").is_err());
+    }
+
     #[test]
     fn segment_range_initialization() {
         let t = Time::from_rfc3339("2024-03-01T13:46:00Z").unwrap();
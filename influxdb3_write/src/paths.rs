@@ -68,6 +68,40 @@ impl ParquetFilePath {
         Self(path)
     }
 
+    /// Creates a path in the directory of a [`ParquetLayout::Template`], filling in its `{db}`,
+    /// `{table}`, and `{date}` placeholders. Placeholders are filled in one pass, so names that
+    /// contain braces are kept as they are.
+    ///
+    /// [`ParquetLayout::Template`]: crate::ParquetLayout::Template
+    pub fn new_from_template(
+        template: &str,
+        db_name: &str,
+        table_name: &str,
+        date: DateTime<Utc>,
+        file_number: u32,
+    ) -> Self {
+        let mut parts = template.split('{');
+        let mut dir = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            match part.split_once('}') {
+                Some(("db", rest)) => dir.extend([db_name, rest]),
+                Some(("table", rest)) => dir.extend([table_name, rest]),
+                Some(("date", rest)) => {
+                    dir.push_str(&date.format("%Y-%m-%d").to_string());
+                    dir.push_str(rest);
+                }
+                _ => dir.extend(["{", part]),
+            }
+        }
+
+        let path = ObjPath::from(format!(
+            "dbs/{db_name}/{dir}/{:010}.{}",
+            object_store_file_stem(file_number),
+            PARQUET_FILE_EXTENSION
+        ));
+        Self(path)
+    }
+
     pub fn new_with_partition_key(
         db_name: &str,
         table_name: &str,
@@ -82,6 +116,30 @@ impl ParquetFilePath {
         ));
         Self(path)
     }
+
+    /// Returns the path, next to this one, that the data of this file is written to once the
+    /// late arriving data of the given segment has been merged into it.
+    pub fn merged_with(&self, late_segment_id: SegmentId) -> Self {
+        let file_name = format!(
+            "{}-{:010}.{}",
+            self.0
+                .filename()
+                .and_then(|name| name.strip_suffix(&format!(".{PARQUET_FILE_EXTENSION}")))
+                .unwrap_or_default(),
+            object_store_file_stem(late_segment_id.0),
+            PARQUET_FILE_EXTENSION
+        );
+        let mut parts = self.0.parts().collect::<Vec<_>>();
+        parts.pop();
+        parts.push(file_name.into());
+        Self(parts.into_iter().collect())
+    }
+}
+
+impl From<&str> for ParquetFilePath {
+    fn from(path: &str) -> Self {
+        Self(ObjPath::from(path))
+    }
 }
 
 impl Deref for ParquetFilePath {
@@ -162,6 +220,82 @@ impl AsRef<ObjPath> for SegmentInfoFilePath {
     }
 }
 
+/// Location that parquet files failing checksum verification are moved to, keeping the
+/// original path underneath it, and that WAL writes which could not be replayed are written to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuarantineFilePath(ObjPath);
+
+impl QuarantineFilePath {
+    pub fn new(parquet_file_path: &str) -> Self {
+        Self(ObjPath::from(format!("quarantine/{parquet_file_path}")))
+    }
+
+    pub fn new_wal_segment(segment_id: SegmentId) -> Self {
+        Self(ObjPath::from(format!(
+            "quarantine/wal/{:010}.json",
+            object_store_file_stem(segment_id.0)
+        )))
+    }
+
+    pub fn dir() -> Self {
+        Self(ObjPath::from("quarantine"))
+    }
+
+    /// Returns the path a quarantined parquet file was moved from, or `None` if the location
+    /// isn't a quarantined parquet file.
+    pub fn original_parquet_file_path(location: &ObjPath) -> Option<&str> {
+        if location.extension() != Some(PARQUET_FILE_EXTENSION) {
+            return None;
+        }
+        location.as_ref().strip_prefix("quarantine/")
+    }
+}
+
+impl Deref for QuarantineFilePath {
+    type Target = ObjPath;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<ObjPath> for QuarantineFilePath {
+    fn as_ref(&self) -> &ObjPath {
+        &self.0
+    }
+}
+
+/// Location of the fencing epoch held by the server that is allowed to persist to the object
+/// store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FenceFilePath(ObjPath);
+
+impl FenceFilePath {
+    pub fn new() -> Self {
+        Self(ObjPath::from("fence.json"))
+    }
+}
+
+impl Default for FenceFilePath {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for FenceFilePath {
+    type Target = ObjPath;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<ObjPath> for FenceFilePath {
+    fn as_ref(&self) -> &ObjPath {
+        &self.0
+    }
+}
+
 #[test]
 fn catalog_file_path_new() {
     assert_eq!(
@@ -183,6 +317,31 @@ fn parquet_file_path_new() {
     );
 }
 
+#[test]
+fn parquet_file_path_new_from_template() {
+    let date = Utc.with_ymd_and_hms(2038, 1, 19, 3, 14, 7).unwrap();
+    assert_eq!(
+        *ParquetFilePath::new_from_template(
+            crate::HIVE_PARQUET_LAYOUT,
+            "my_db",
+            "my_table",
+            date,
+            0
+        ),
+        ObjPath::from("dbs/my_db/my_table/date=2038-01-19/4294967295.parquet")
+    );
+    assert_eq!(
+        *ParquetFilePath::new_from_template(
+            "db={db}/table={table}/{date}",
+            "my_db",
+            "{date}",
+            date,
+            0
+        ),
+        ObjPath::from("dbs/my_db/db=my_db/table={date}/2038-01-19/4294967295.parquet")
+    );
+}
+
 #[test]
 fn parquet_file_percent_encoded() {
     assert_eq!(
@@ -213,3 +372,39 @@ fn segment_wal_file_path_new() {
         PathBuf::from("dir/0000000000.wal").as_ref()
     );
 }
+
+#[test]
+fn quarantine_file_path_new() {
+    assert_eq!(
+        *QuarantineFilePath::new("dbs/my_db/my_table/2038-01-19/4294967295.parquet"),
+        ObjPath::from("quarantine/dbs/my_db/my_table/2038-01-19/4294967295.parquet")
+    );
+}
+
+#[test]
+fn quarantine_wal_segment_path_new() {
+    assert_eq!(
+        *QuarantineFilePath::new_wal_segment(SegmentId::new(0)),
+        ObjPath::from("quarantine/wal/4294967295.json")
+    );
+}
+
+#[test]
+fn quarantine_file_path_original_parquet_file_path() {
+    let parquet_file_path = "dbs/my_db/my_table/2038-01-19/4294967295.parquet";
+    assert_eq!(
+        QuarantineFilePath::original_parquet_file_path(&QuarantineFilePath::new(parquet_file_path)),
+        Some(parquet_file_path)
+    );
+    assert_eq!(
+        QuarantineFilePath::original_parquet_file_path(&QuarantineFilePath::new_wal_segment(
+            SegmentId::new(0)
+        )),
+        None
+    );
+}
+
+#[test]
+fn fence_file_path_new() {
+    assert_eq!(*FenceFilePath::new(), ObjPath::from("fence.json"));
+}
@@ -4,12 +4,18 @@
 use crate::catalog::Catalog;
 use crate::catalog::InnerCatalog;
 use crate::paths::CatalogFilePath;
+use crate::paths::FenceFilePath;
 use crate::paths::ParquetFilePath;
+use crate::paths::QuarantineFilePath;
 use crate::paths::SegmentInfoFilePath;
+use crate::ParquetFile;
+use crate::ParquetLayout;
 use crate::PersistedCatalog;
 use crate::PersistedSegment;
 use crate::Persister;
+use crate::QuarantinedWrite;
 use crate::SegmentId;
+use crate::SequenceNumber;
 use arrow::datatypes::SchemaRef;
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
@@ -23,16 +29,22 @@ use datafusion::physical_plan::SendableRecordBatchStream;
 use futures_util::pin_mut;
 use futures_util::stream::StreamExt;
 use futures_util::stream::TryStreamExt;
+use metric::U64Counter;
 use object_store::path::Path as ObjPath;
-use object_store::ObjectStore;
+use object_store::{ObjectStore, PutMode, PutOptions, UpdateVersion};
+use observability_deps::tracing::{error, warn};
 use parquet::arrow::ArrowWriter;
 use parquet::basic::Compression;
 use parquet::file::properties::WriterProperties;
 use parquet::format::FileMetaData;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use sha2::Sha256;
 use std::any::Any;
 use std::io::Write;
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::sync::Mutex;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -53,6 +65,16 @@ pub enum Error {
 
     #[error("parse int error: {0}")]
     ParseInt(#[from] std::num::ParseIntError),
+
+    #[error("fenced out: this server holds fencing epoch {held} but the current one is {current}")]
+    Fenced { held: u64, current: u64 },
+
+    #[error("checksum mismatch for parquet file {path}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        path: String,
+        expected: String,
+        actual: String,
+    },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -61,6 +83,40 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub struct PersisterImpl {
     object_store: Arc<dyn ObjectStore>,
     pub(crate) mem_pool: Arc<dyn MemoryPool>,
+    checksum_mismatches: U64Counter,
+    parquet_layout: ParquetLayout,
+    /// The fencing epoch acquired by this server, if it has acquired one
+    fence: Mutex<Option<HeldFence>>,
+    /// The segment id and sequence number of the newest catalog loaded or persisted
+    newest_catalog: Mutex<Option<(SegmentId, SequenceNumber)>>,
+}
+
+/// The contents of the fence file. The server holding the highest epoch is the only one allowed
+/// to persist catalogs and segments.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
+struct Fence {
+    epoch: u64,
+}
+
+/// The fencing epoch held by this server, along with the version of the fence file it last
+/// wrote, which its next write of the fence file is conditional on
+#[derive(Debug, Clone)]
+struct HeldFence {
+    epoch: u64,
+    /// `None` if the object store doesn't support conditional updates, in which case the fence
+    /// file can only be read back to check it
+    version: Option<UpdateVersion>,
+}
+
+/// The contents of a persisted catalog file: the catalog, along with the fencing epoch held by
+/// the server that persisted it. Catalogs persisted before epochs were recorded have an epoch
+/// of 0.
+#[derive(Debug, Serialize, Deserialize)]
+struct CatalogFile<C> {
+    #[serde(flatten)]
+    catalog: C,
+    #[serde(default)]
+    fencing_epoch: u64,
 }
 
 impl PersisterImpl {
@@ -68,9 +124,206 @@ impl PersisterImpl {
         Self {
             object_store,
             mem_pool: Arc::new(UnboundedMemoryPool::default()),
+            checksum_mismatches: checksum_mismatch_counter(&metric::Registry::default()),
+            parquet_layout: ParquetLayout::default(),
+            fence: Mutex::new(None),
+            newest_catalog: Mutex::new(None),
         }
     }
 
+    /// Report checksum mismatches found when loading parquet files to the given registry
+    pub fn with_metrics(mut self, metrics: &metric::Registry) -> Self {
+        self.checksum_mismatches = checksum_mismatch_counter(metrics);
+        self
+    }
+
+    /// Persist parquet files with paths in the given layout
+    pub fn with_parquet_layout(mut self, parquet_layout: ParquetLayout) -> Self {
+        self.parquet_layout = parquet_layout;
+        self
+    }
+
+    /// Takes over persistence by writing a fencing epoch one higher than the current one. After
+    /// this, catalogs, segments, and parquet files are only persisted while the epoch in object
+    /// storage is still the one acquired, so a server that was failed over from can no longer
+    /// persist once its replacement has started. The fence file is written conditionally on the
+    /// version that was read, so of two servers starting at once only one acquires each epoch.
+    /// Returns the acquired epoch.
+    pub async fn acquire_fence(&self) -> Result<u64> {
+        let mut held = self.fence.lock().await;
+        loop {
+            let (epoch, mode) = match self.load_fence().await? {
+                Some((fence, version)) => (fence.epoch + 1, PutMode::Update(version)),
+                None => (1, PutMode::Create),
+            };
+            match self.put_fence(epoch, mode).await {
+                Ok(version) => {
+                    *held = Some(HeldFence {
+                        epoch,
+                        version: Some(version),
+                    });
+                    return Ok(epoch);
+                }
+                // another server wrote the fence after it was read, so acquire the epoch after its
+                Err(Error::ObjectStore(
+                    object_store::Error::Precondition { .. }
+                    | object_store::Error::AlreadyExists { .. },
+                )) => continue,
+                Err(Error::ObjectStore(object_store::Error::NotImplemented)) => {
+                    warn!(
+                        "object store doesn't support conditional updates, servers that start at \
+                        the same time may both acquire the fence"
+                    );
+                    self.put_fence(epoch, PutMode::Overwrite).await?;
+                    *held = Some(HeldFence {
+                        epoch,
+                        version: None,
+                    });
+                    return Ok(epoch);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn load_fence(&self) -> Result<Option<(Fence, UpdateVersion)>> {
+        match self.object_store.get(FenceFilePath::new().as_ref()).await {
+            Ok(get_result) => {
+                let version = UpdateVersion {
+                    e_tag: get_result.meta.e_tag.clone(),
+                    version: get_result.meta.version.clone(),
+                };
+                let fence = serde_json::from_slice(&get_result.bytes().await?)?;
+                Ok(Some((fence, version)))
+            }
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the fence file with the given epoch, returning the version written
+    async fn put_fence(&self, epoch: u64, mode: PutMode) -> Result<UpdateVersion> {
+        let json = serde_json::to_vec_pretty(&Fence { epoch })?;
+        let result = self
+            .object_store
+            .put_opts(
+                FenceFilePath::new().as_ref(),
+                Bytes::from(json),
+                PutOptions {
+                    mode,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        Ok(UpdateVersion {
+            e_tag: result.e_tag,
+            version: result.version,
+        })
+    }
+
+    /// Returns an error if a fencing epoch was acquired and another server has since acquired a
+    /// higher one, or else the epoch held, 0 if none was acquired. The check rewrites the fence
+    /// file conditionally on the version this server last wrote, so a server that acquires the
+    /// fence concurrently either fails to or makes the check fail.
+    async fn check_fence(&self) -> Result<u64> {
+        let mut held = self.fence.lock().await;
+        let Some(HeldFence { epoch, version }) = held.clone() else {
+            return Ok(0);
+        };
+
+        let result = match version {
+            Some(version) => self.put_fence(epoch, PutMode::Update(version)).await,
+            None => Err(Error::ObjectStore(object_store::Error::NotImplemented)),
+        };
+        match result {
+            Ok(version) => {
+                *held = Some(HeldFence {
+                    epoch,
+                    version: Some(version),
+                });
+                return Ok(epoch);
+            }
+            Err(Error::ObjectStore(object_store::Error::NotImplemented)) => {
+                // without conditional updates the fence can only be read back
+                *held = Some(HeldFence {
+                    epoch,
+                    version: None,
+                });
+                let current = self.load_fence().await?.map_or(0, |(fence, _)| fence.epoch);
+                if current == epoch {
+                    return Ok(epoch);
+                }
+            }
+            Err(Error::ObjectStore(object_store::Error::Precondition { .. })) => {}
+            Err(e) => return Err(e),
+        }
+
+        let current = self.load_fence().await?.map_or(0, |(fence, _)| fence.epoch);
+        error!(
+            held = epoch,
+            current, "fenced out of persisting to object storage"
+        );
+        Err(Error::Fenced {
+            held: epoch,
+            current,
+        })
+    }
+
+    /// Loads a persisted parquet file and verifies its contents against the checksum recorded
+    /// when it was persisted. A file that doesn't match is moved under the quarantine directory
+    /// so that it is kept for inspection, and an error is returned.
+    pub async fn load_verified_parquet_file(&self, parquet_file: &ParquetFile) -> Result<Bytes> {
+        let path = ObjPath::from(parquet_file.path.as_str());
+        let bytes = self.object_store.get(&path).await?.bytes().await?;
+
+        let Some(expected) = &parquet_file.checksum else {
+            return Ok(bytes);
+        };
+        let actual = parquet_checksum(&bytes);
+        if &actual == expected {
+            return Ok(bytes);
+        }
+
+        self.checksum_mismatches.inc(1);
+        error!(
+            path = %parquet_file.path,
+            %expected,
+            %actual,
+            "parquet file checksum mismatch, quarantining file"
+        );
+        let quarantine_path = QuarantineFilePath::new(&parquet_file.path);
+        let quarantined = match self.check_fence().await {
+            Ok(_) => self
+                .object_store
+                .rename(&path, &quarantine_path)
+                .await
+                .map_err(Error::from),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = quarantined {
+            warn!(path = %parquet_file.path, error = %e, "failed to quarantine parquet file");
+        }
+
+        Err(Error::ChecksumMismatch {
+            path: parquet_file.path.clone(),
+            expected: expected.clone(),
+            actual,
+        })
+    }
+
+    /// Returns the paths that the parquet files moved to quarantine were persisted at.
+    pub async fn load_quarantined_parquet_files(&self) -> Result<Vec<String>> {
+        let mut list = self.object_store.list(Some(&QuarantineFilePath::dir()));
+        let mut paths = vec![];
+        while let Some(item) = list.next().await {
+            let item = item?;
+            if let Some(path) = QuarantineFilePath::original_parquet_file_path(&item.location) {
+                paths.push(path.to_string());
+            }
+        }
+        Ok(paths)
+    }
+
     async fn serialize_to_parquet(
         &self,
         batches: SendableRecordBatchStream,
@@ -79,6 +332,20 @@ impl PersisterImpl {
     }
 }
 
+fn checksum_mismatch_counter(metrics: &metric::Registry) -> U64Counter {
+    metrics
+        .register_metric::<U64Counter>(
+            "influxdb3_parquet_checksum_mismatches",
+            "Number of persisted parquet files that failed checksum verification when loaded",
+        )
+        .recorder(&[])
+}
+
+/// Returns the hex encoded SHA-256 of the given parquet file bytes
+pub fn parquet_checksum(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
 pub async fn serialize_to_parquet(
     mem_pool: Arc<dyn MemoryPool>,
     batches: SendableRecordBatchStream,
@@ -150,7 +417,10 @@ impl Persister for PersisterImpl {
             None => Ok(None),
             Some(path) => {
                 let bytes = self.object_store.get(&path).await?.bytes().await?;
-                let catalog: InnerCatalog = serde_json::from_slice(&bytes)?;
+                let CatalogFile {
+                    catalog,
+                    fencing_epoch,
+                } = serde_json::from_slice::<CatalogFile<InnerCatalog>>(&bytes)?;
                 let file_name = path
                     .filename()
                     // NOTE: this holds so long as CatalogFilePath is used
@@ -160,9 +430,11 @@ impl Persister for PersisterImpl {
                     .trim_end_matches(format!(".{}", crate::paths::CATALOG_FILE_EXTENSION).as_str())
                     .parse::<u32>()?;
                 let segment_id = SegmentId::new(u32::MAX - parsed_number);
+                *self.newest_catalog.lock().await = Some((segment_id, catalog.sequence_number()));
                 Ok(Some(PersistedCatalog {
                     segment_id,
                     catalog,
+                    fencing_epoch,
                 }))
             }
         }
@@ -229,39 +501,96 @@ impl Persister for PersisterImpl {
     }
 
     async fn persist_catalog(&self, segment_id: SegmentId, catalog: Catalog) -> Result<()> {
+        let fencing_epoch = self.check_fence().await?;
+
+        // The catalog can be persisted outside of persisting a segment, so a segment may persist
+        // a copy of the catalog taken before a newer one was written. Keep the newest catalog as
+        // the one that is loaded.
+        let mut newest_catalog = self.newest_catalog.lock().await;
+        let sequence = catalog.sequence_number();
+        let segment_id = match *newest_catalog {
+            Some((_, newest_sequence)) if sequence < newest_sequence => return Ok(()),
+            Some((newest_segment_id, _)) => segment_id.max(newest_segment_id),
+            None => segment_id,
+        };
+
         let catalog_path = CatalogFilePath::new(segment_id);
-        let json = serde_json::to_vec_pretty(&catalog.into_inner())?;
+        let json = serde_json::to_vec_pretty(&CatalogFile {
+            catalog: catalog.into_inner(),
+            fencing_epoch,
+        })?;
         self.object_store
             .put(catalog_path.as_ref(), Bytes::from(json))
             .await?;
+        *newest_catalog = Some((segment_id, sequence));
         Ok(())
     }
 
     async fn persist_segment(&self, persisted_segment: &PersistedSegment) -> Result<()> {
+        let fencing_epoch = self.check_fence().await?;
         let segment_file_path = SegmentInfoFilePath::new(persisted_segment.segment_id);
-        let json = serde_json::to_vec_pretty(persisted_segment)?;
+        let json = serde_json::to_vec_pretty(&PersistedSegment {
+            fencing_epoch,
+            ..persisted_segment.clone()
+        })?;
         self.object_store
             .put(segment_file_path.as_ref(), Bytes::from(json))
             .await?;
         Ok(())
     }
 
+    async fn persist_quarantined_writes(
+        &self,
+        segment_id: SegmentId,
+        writes: &[QuarantinedWrite],
+    ) -> Result<()> {
+        self.check_fence().await?;
+        let path = QuarantineFilePath::new_wal_segment(segment_id);
+        let json = serde_json::to_vec_pretty(writes)?;
+        self.object_store
+            .put(path.as_ref(), Bytes::from(json))
+            .await?;
+        Ok(())
+    }
+
     async fn persist_parquet_file(
         &self,
         path: ParquetFilePath,
         record_batch: SendableRecordBatchStream,
-    ) -> Result<(u64, FileMetaData)> {
+    ) -> Result<(u64, FileMetaData, String)> {
         let parquet = self.serialize_to_parquet(record_batch).await?;
         let bytes_written = parquet.bytes.len() as u64;
+        let checksum = parquet_checksum(&parquet.bytes);
+        // checked after serializing, which can take a while, so it's as close to the put as it
+        // can be
+        self.check_fence().await?;
         self.object_store.put(path.as_ref(), parquet.bytes).await?;
 
-        Ok((bytes_written, parquet.meta_data))
+        Ok((bytes_written, parquet.meta_data, checksum))
+    }
+
+    async fn delete_segment(&self, segment_id: SegmentId) -> Result<()> {
+        self.check_fence().await?;
+        self.object_store
+            .delete(&SegmentInfoFilePath::new(segment_id))
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_parquet_file(&self, path: ParquetFilePath) -> Result<()> {
+        self.check_fence().await?;
+        self.object_store.delete(path.as_ref()).await?;
+        Ok(())
     }
 
     fn object_store(&self) -> Arc<dyn ObjectStore> {
         self.object_store.clone()
     }
 
+    fn parquet_layout(&self) -> ParquetLayout {
+        self.parquet_layout.clone()
+    }
+
     fn as_any(&self) -> &dyn Any {
         self as &dyn Any
     }
@@ -327,6 +656,7 @@ impl<W: Write + Send> TrackedMemoryArrowWriter<W> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fault_injection::{Fault, FaultInjectingStore};
     use object_store::memory::InMemory;
     use {
         arrow::array::Int32Array, arrow::datatypes::DataType, arrow::datatypes::Field,
@@ -381,6 +711,42 @@ mod tests {
         assert!(!catalog.catalog.db_exists("my_db"));
     }
 
+    #[tokio::test]
+    async fn persist_catalog_keeps_newest_catalog() {
+        let persister = PersisterImpl::new(Arc::new(InMemory::new()));
+        let catalog = Catalog::new();
+        let stale = Catalog::from_inner(catalog.clone_inner());
+        catalog.set_strict_tables("my_db", true).unwrap();
+
+        persister
+            .persist_catalog(
+                SegmentId::new(1),
+                Catalog::from_inner(catalog.clone_inner()),
+            )
+            .await
+            .unwrap();
+
+        // a catalog persisted outside of a segment is written over the newest one
+        catalog.set_provenance_columns("my_db", true).unwrap();
+        persister
+            .persist_catalog(
+                SegmentId::new(0),
+                Catalog::from_inner(catalog.clone_inner()),
+            )
+            .await
+            .unwrap();
+
+        // a segment persisting a copy taken before the newest catalog doesn't replace it
+        persister
+            .persist_catalog(SegmentId::new(2), stale)
+            .await
+            .unwrap();
+
+        let loaded = persister.load_catalog().await.unwrap().unwrap();
+        assert_eq!(loaded.segment_id, SegmentId::new(1));
+        assert_eq!(loaded.catalog, catalog.clone_inner());
+    }
+
     #[tokio::test]
     async fn persist_segment_info_file() {
         let local_disk =
@@ -394,6 +760,8 @@ mod tests {
             segment_max_time: 1,
             segment_row_count: 0,
             segment_parquet_size_bytes: 0,
+            late_data: false,
+            fencing_epoch: 0,
         };
 
         persister.persist_segment(&info_file).await.unwrap();
@@ -412,6 +780,8 @@ mod tests {
             segment_max_time: 1,
             segment_row_count: 0,
             segment_parquet_size_bytes: 0,
+            late_data: false,
+            fencing_epoch: 0,
         };
         let info_file_2 = PersistedSegment {
             segment_id: SegmentId::new(1),
@@ -421,6 +791,8 @@ mod tests {
             segment_max_time: 1,
             segment_row_count: 0,
             segment_parquet_size_bytes: 0,
+            late_data: false,
+            fencing_epoch: 0,
         };
         let info_file_3 = PersistedSegment {
             segment_id: SegmentId::new(2),
@@ -430,6 +802,8 @@ mod tests {
             segment_max_time: 1,
             segment_row_count: 0,
             segment_parquet_size_bytes: 0,
+            late_data: false,
+            fencing_epoch: 0,
         };
 
         persister.persist_segment(&info_file).await.unwrap();
@@ -456,6 +830,8 @@ mod tests {
             segment_max_time: 1,
             segment_row_count: 0,
             segment_parquet_size_bytes: 0,
+            late_data: false,
+            fencing_epoch: 0,
         };
         persister.persist_segment(&info_file).await.unwrap();
         let segments = persister.load_segments(2).await.unwrap();
@@ -479,6 +855,8 @@ mod tests {
                 segment_max_time: 1,
                 segment_row_count: 0,
                 segment_parquet_size_bytes: 0,
+                late_data: false,
+                fencing_epoch: 0,
             };
             persister.persist_segment(&info_file).await.unwrap();
         }
@@ -543,7 +921,7 @@ mod tests {
         stream_builder.tx().send(Ok(batch2)).await.unwrap();
 
         let path = ParquetFilePath::new("db_one", "table_one", Utc::now(), 1);
-        let (bytes_written, meta) = persister
+        let (bytes_written, meta, _) = persister
             .persist_parquet_file(path.clone(), stream_builder.build())
             .await
             .unwrap();
@@ -557,4 +935,297 @@ mod tests {
         assert!(!bytes.is_empty());
         assert_eq!(bytes.len() as u64, bytes_written);
     }
+
+    #[tokio::test]
+    async fn fenced_out_persister_cannot_persist() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let old_persister = PersisterImpl::new(Arc::clone(&object_store));
+        let new_persister = PersisterImpl::new(Arc::clone(&object_store));
+
+        assert_eq!(old_persister.acquire_fence().await.unwrap(), 1);
+        old_persister
+            .persist_catalog(SegmentId::new(0), Catalog::new())
+            .await
+            .unwrap();
+
+        // a failover starts a new server, which takes over persistence
+        assert_eq!(new_persister.acquire_fence().await.unwrap(), 2);
+
+        let err = old_persister
+            .persist_catalog(SegmentId::new(1), Catalog::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Fenced {
+                held: 1,
+                current: 2
+            }
+        ));
+        let segment = PersistedSegment {
+            segment_id: SegmentId::new(1),
+            segment_wal_size_bytes: 0,
+            segment_parquet_size_bytes: 0,
+            segment_row_count: 0,
+            segment_min_time: 0,
+            segment_max_time: 0,
+            databases: HashMap::new(),
+            late_data: false,
+            fencing_epoch: 0,
+        };
+        assert!(matches!(
+            old_persister.persist_segment(&segment).await,
+            Err(Error::Fenced { .. })
+        ));
+
+        assert!(matches!(
+            old_persister
+                .persist_quarantined_writes(SegmentId::new(1), &[])
+                .await,
+            Err(Error::Fenced { .. })
+        ));
+
+        new_persister
+            .persist_catalog(SegmentId::new(1), Catalog::new())
+            .await
+            .unwrap();
+        new_persister.persist_segment(&segment).await.unwrap();
+
+        // the epoch of the server that persisted them is recorded
+        let catalog = new_persister.load_catalog().await.unwrap().unwrap();
+        assert_eq!(catalog.segment_id, SegmentId::new(1));
+        assert_eq!(catalog.fencing_epoch, 2);
+        let segments = new_persister.load_segments(10).await.unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].fencing_epoch, 2);
+    }
+
+    #[tokio::test]
+    async fn fencing_falls_back_to_reading_without_conditional_updates() {
+        // the local file system doesn't support conditional updates
+        let object_store: Arc<dyn ObjectStore> =
+            Arc::new(LocalFileSystem::new_with_prefix(test_helpers::tmp_dir().unwrap()).unwrap());
+        let old_persister = PersisterImpl::new(Arc::clone(&object_store));
+        let new_persister = PersisterImpl::new(Arc::clone(&object_store));
+
+        assert_eq!(old_persister.acquire_fence().await.unwrap(), 1);
+        old_persister
+            .persist_catalog(SegmentId::new(0), Catalog::new())
+            .await
+            .unwrap();
+
+        assert_eq!(new_persister.acquire_fence().await.unwrap(), 2);
+        let err = old_persister
+            .persist_catalog(SegmentId::new(1), Catalog::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Fenced {
+                held: 1,
+                current: 2
+            }
+        ));
+        new_persister
+            .persist_catalog(SegmentId::new(1), Catalog::new())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn fenced_out_persister_cannot_overwrite_parquet_file() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let old_persister = PersisterImpl::new(Arc::clone(&object_store));
+        let new_persister = PersisterImpl::new(Arc::clone(&object_store));
+        async fn stream(ids: Vec<i32>) -> SendableRecordBatchStream {
+            let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+            let stream_builder = RecordBatchReceiverStreamBuilder::new(Arc::clone(&schema), 1);
+            let batch =
+                RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(ids))]).unwrap();
+            stream_builder.tx().send(Ok(batch)).await.unwrap();
+            stream_builder.build()
+        }
+        let path = ParquetFilePath::new("db_one", "table_one", Utc::now(), 1);
+
+        old_persister.acquire_fence().await.unwrap();
+        // a failover starts a new server, which takes over persistence and persists the file
+        new_persister.acquire_fence().await.unwrap();
+        let (bytes_written, ..) = new_persister
+            .persist_parquet_file(path.clone(), stream(vec![1, 2, 3]).await)
+            .await
+            .unwrap();
+
+        let err = old_persister
+            .persist_parquet_file(path.clone(), stream(vec![4]).await)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Fenced {
+                held: 1,
+                current: 2
+            }
+        ));
+
+        // the file persisted by the new server is left as it was
+        let bytes = new_persister.load_parquet_file(path).await.unwrap();
+        assert_eq!(bytes.len() as u64, bytes_written);
+    }
+
+    #[tokio::test]
+    async fn quarantines_parquet_file_with_checksum_mismatch() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let metrics = metric::Registry::default();
+        let persister = PersisterImpl::new(Arc::clone(&object_store)).with_metrics(&metrics);
+
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        let stream_builder = RecordBatchReceiverStreamBuilder::new(schema.clone(), 5);
+        let id_array = Int32Array::from(vec![1, 2, 3, 4, 5]);
+        let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(id_array)]).unwrap();
+        stream_builder.tx().send(Ok(batch)).await.unwrap();
+
+        let path = ParquetFilePath::new("db_one", "table_one", Utc::now(), 1);
+        let (size_bytes, meta, checksum) = persister
+            .persist_parquet_file(path.clone(), stream_builder.build())
+            .await
+            .unwrap();
+        let parquet_file = ParquetFile {
+            path: path.to_string(),
+            size_bytes,
+            row_count: meta.num_rows as u64,
+            min_time: 0,
+            max_time: 0,
+            checksum: Some(checksum),
+            column_stats: Default::default(),
+            column_sizes: Default::default(),
+        };
+
+        // an intact file loads
+        let bytes = persister
+            .load_verified_parquet_file(&parquet_file)
+            .await
+            .unwrap();
+        assert_eq!(bytes.len() as u64, size_bytes);
+
+        // corrupt the file and check that it is refused and moved to quarantine
+        object_store
+            .put(&path, Bytes::from_static(b"not a parquet file"))
+            .await
+            .unwrap();
+        let err = persister
+            .load_verified_parquet_file(&parquet_file)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ChecksumMismatch { .. }));
+        assert!(object_store.head(&path).await.is_err());
+        object_store
+            .head(&QuarantineFilePath::new(&parquet_file.path))
+            .await
+            .unwrap();
+
+        // quarantined writes from the wal are not mistaken for parquet files
+        persister
+            .persist_quarantined_writes(SegmentId::new(1), &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            persister.load_quarantined_parquet_files().await.unwrap(),
+            vec![parquet_file.path.clone()]
+        );
+
+        let mismatches = metrics
+            .get_instrument::<metric::Metric<U64Counter>>("influxdb3_parquet_checksum_mismatches")
+            .unwrap()
+            .get_observer(&metric::Attributes::from(&[]))
+            .unwrap()
+            .fetch();
+        assert_eq!(mismatches, 1);
+    }
+
+    #[tokio::test]
+    async fn persist_segment_surfaces_injected_failures() {
+        let object_store = Arc::new(FaultInjectingStore::new());
+        let persister = PersisterImpl::new(Arc::clone(&object_store) as Arc<dyn ObjectStore>);
+        object_store.inject(
+            SegmentInfoFilePath::dir().to_string(),
+            Fault {
+                fail_every: Some(1),
+                ..Default::default()
+            },
+        );
+
+        let info_file = PersistedSegment {
+            segment_id: SegmentId::new(0),
+            segment_wal_size_bytes: 0,
+            databases: HashMap::new(),
+            segment_min_time: 0,
+            segment_max_time: 1,
+            segment_row_count: 0,
+            segment_parquet_size_bytes: 0,
+            late_data: false,
+            fencing_epoch: 0,
+        };
+        assert!(persister.persist_segment(&info_file).await.is_err());
+        // listing the segments is faulted as well
+        assert!(persister.load_segments(1).await.is_err());
+
+        // once the store recovers the same segment can be persisted
+        object_store.clear();
+        assert!(persister.load_segments(1).await.unwrap().is_empty());
+        persister.persist_segment(&info_file).await.unwrap();
+        assert_eq!(persister.load_segments(1).await.unwrap(), vec![info_file]);
+    }
+
+    #[tokio::test]
+    async fn partial_parquet_write_fails_verification() {
+        let object_store = Arc::new(FaultInjectingStore::new());
+        let persister = PersisterImpl::new(Arc::clone(&object_store) as Arc<dyn ObjectStore>);
+
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        let id_array = Int32Array::from(vec![1, 2, 3, 4, 5]);
+        let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(id_array)]).unwrap();
+        let path = ParquetFilePath::new("db_one", "table_one", Utc::now(), 1);
+
+        // every second upload is cut off part way through
+        object_store.inject(
+            "dbs/",
+            Fault {
+                fail_every: Some(2),
+                partial_write_bytes: Some(10),
+                ..Default::default()
+            },
+        );
+        let stream_builder = RecordBatchReceiverStreamBuilder::new(schema.clone(), 5);
+        stream_builder.tx().send(Ok(batch.clone())).await.unwrap();
+        let (size_bytes, meta, checksum) = persister
+            .persist_parquet_file(path.clone(), stream_builder.build())
+            .await
+            .unwrap();
+
+        let stream_builder = RecordBatchReceiverStreamBuilder::new(schema.clone(), 5);
+        stream_builder.tx().send(Ok(batch)).await.unwrap();
+        assert!(persister
+            .persist_parquet_file(path.clone(), stream_builder.build())
+            .await
+            .is_err());
+        object_store.clear();
+
+        // the truncated file left behind by the failed upload is caught when loaded
+        assert_eq!(object_store.head(&path).await.unwrap().size, 10);
+        let parquet_file = ParquetFile {
+            path: path.to_string(),
+            size_bytes,
+            row_count: meta.num_rows as u64,
+            min_time: 0,
+            max_time: 0,
+            checksum: Some(checksum),
+            column_stats: Default::default(),
+            column_sizes: Default::default(),
+        };
+        let err = persister
+            .load_verified_parquet_file(&parquet_file)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ChecksumMismatch { .. }));
+    }
 }
@@ -677,6 +677,7 @@ mod tests {
             lp: "cpu host=a val=10i 10".to_string(),
             default_time: 1,
             precision: Precision::Nanosecond,
+            source: None,
         });
         writer.write_batch(vec![wal_op.clone()]).unwrap();
 
@@ -695,6 +696,7 @@ mod tests {
             lp: "cpu host=a val=10i 10".to_string(),
             default_time: 1,
             precision: Precision::Nanosecond,
+            source: None,
         });
 
         // open the file, write and close it
@@ -735,6 +737,7 @@ mod tests {
             lp: "cpu host=a val=10i 10".to_string(),
             default_time: 1,
             precision: Precision::Nanosecond,
+            source: None,
         });
 
         let wal = WalImpl::new(dir.clone()).unwrap();
@@ -769,30 +772,35 @@ mod tests {
                 lp: "cpu,host=a val=1i 1".to_string(),
                 default_time: 1,
                 precision: Precision::Second,
+                source: None,
             }),
             WalOp::LpWrite(LpWriteOp {
                 db_name: "foo".to_string(),
                 lp: "cpu,host=b val=2i 1000".to_string(),
                 default_time: 1,
                 precision: Precision::Millisecond,
+                source: None,
             }),
             WalOp::LpWrite(LpWriteOp {
                 db_name: "foo".to_string(),
                 lp: "cpu,host=c val=3i 1000000".to_string(),
                 default_time: 1,
                 precision: Precision::Microsecond,
+                source: None,
             }),
             WalOp::LpWrite(LpWriteOp {
                 db_name: "foo".to_string(),
                 lp: "cpu,host=d val=4i 1000000000".to_string(),
                 default_time: 1,
                 precision: Precision::Nanosecond,
+                source: None,
             }),
             WalOp::LpWrite(LpWriteOp {
                 db_name: "foo".to_string(),
                 lp: "cpu,host=e val=5i 1".to_string(),
                 default_time: 1,
                 precision: Precision::Auto,
+                source: None,
             }),
         ];
 
@@ -825,6 +833,8 @@ mod tests {
         let buffer = crate::write_buffer::buffer_segment::load_buffer_from_segment(
             &catalog,
             wal.open_segment_reader(segment).unwrap(),
+            &mut vec![],
+            |_| true,
         )
         .unwrap()
         .0;
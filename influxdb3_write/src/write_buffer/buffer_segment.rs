@@ -2,7 +2,7 @@
 //! single WAL segment. Only one segment should be open for writes in the write buffer at any
 //! given time.
 
-use crate::catalog::Catalog;
+use crate::catalog::{Catalog, TIME_COLUMN_NAME};
 use crate::chunk::BufferChunk;
 use crate::paths::ParquetFilePath;
 use crate::write_buffer::flusher::BufferedWriteResult;
@@ -12,25 +12,33 @@ use crate::write_buffer::{
     parse_validate_and_update_catalog, Error, TableBatch, ValidSegmentedData,
 };
 use crate::{
-    wal, write_buffer, write_buffer::Result, DatabaseTables, ParquetFile, PersistedSegment,
-    Persister, SegmentDuration, SegmentId, SegmentRange, SequenceNumber, TableParquetFiles, WalOp,
-    WalSegmentReader, WalSegmentWriter,
+    persister, wal, write_buffer, write_buffer::Result, ColumnSize, ColumnStats, DatabaseTables,
+    ParquetFile, ParquetLayout, PersistedSegment, Persister, QuarantinedWrite, SegmentDuration,
+    SegmentId, SegmentRange, SequenceNumber, StatValue, TableParquetFiles, WalOp, WalSegmentReader,
+    WalSegmentWriter,
 };
-use arrow::datatypes::SchemaRef;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, SchemaRef};
 use arrow::record_batch::RecordBatch;
 use data_types::ChunkId;
 use data_types::ChunkOrder;
 use data_types::TableId;
 use data_types::TransitionPartitionId;
-use data_types::{NamespaceName, PartitionKey};
-use datafusion::logical_expr::Expr;
+use data_types::{NamespaceName, PartitionKey, TimestampMinMax};
+use datafusion::error::DataFusionError;
+use datafusion::logical_expr::{Accumulator, Expr};
+use datafusion::physical_expr::expressions::{MaxAccumulator, MinAccumulator};
 use datafusion_util::stream_from_batches;
 use iox_query::chunk_statistics::create_chunk_statistics;
 use iox_query::frontend::reorg::ReorgPlanner;
 use iox_query::QueryChunk;
 use iox_time::Time;
+use observability_deps::tracing::warn;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::format::FileMetaData;
 use schema::sort::SortKey;
-use std::collections::HashMap;
+use schema::Schema;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
@@ -51,6 +59,8 @@ pub struct OpenBufferSegment {
     //       different structures, we want this to be a representation of approximate memory usage.
     segment_size: usize,
     last_write_time: Instant,
+    // Set when this segment was opened for a time range that had already been persisted
+    late_data: bool,
 }
 
 impl OpenBufferSegment {
@@ -79,6 +89,7 @@ impl OpenBufferSegment {
             segment_size,
             buffered_data,
             last_write_time: Instant::now(),
+            late_data: false,
         }
     }
 
@@ -95,6 +106,16 @@ impl OpenBufferSegment {
         &self.segment_key
     }
 
+    /// Flags this segment as holding late arriving data for a time range that has already been
+    /// persisted. It will be persisted as a separate segment rather than replacing the earlier one.
+    pub(crate) fn mark_late_data(&mut self) {
+        self.late_data = true;
+    }
+
+    pub fn is_late_data(&self) -> bool {
+        self.late_data
+    }
+
     pub fn write_wal_ops(&mut self, write_batch: Vec<WalOp>) -> wal::Result<()> {
         self.segment_writer.write_batch(write_batch)
     }
@@ -133,6 +154,11 @@ impl OpenBufferSegment {
         Ok(())
     }
 
+    /// Returns the buffer for the given table, if it has data in this segment
+    pub(crate) fn table_buffer(&self, db_name: &str, table_name: &str) -> Option<&TableBuffer> {
+        self.buffered_data.table_buffer(db_name, table_name)
+    }
+
     /// Returns the table data as record batches
     pub(crate) fn table_record_batch(
         &self,
@@ -173,16 +199,23 @@ impl OpenBufferSegment {
             catalog.sequence_number(),
             self.buffered_data,
             self.segment_writer.bytes_written(),
+            self.late_data,
             catalog,
         )
     }
 }
 
+/// Replays a WAL segment into a new buffer. Writes that no longer pass validation, for example
+/// because the schema changed after they were written, are skipped and added to
+/// `quarantined_writes` rather than failing the whole load.
 pub(crate) fn load_buffer_from_segment(
     catalog: &Arc<Catalog>,
     mut segment_reader: Box<dyn WalSegmentReader>,
+    quarantined_writes: &mut Vec<QuarantinedWrite>,
+    replay_db: impl Fn(&str) -> bool,
 ) -> Result<(BufferedData, usize)> {
     let mut segment_size = 0;
+    let mut skipped_writes = 0;
     let mut buffered_data = BufferedData::default();
     let segment_key = PartitionKey::from(segment_reader.header().range.key());
     let segment_duration = SegmentDuration::from_range(segment_reader.header().range);
@@ -191,15 +224,46 @@ pub(crate) fn load_buffer_from_segment(
         for wal_op in batch.ops {
             match wal_op {
                 WalOp::LpWrite(write) => {
-                    let mut validated_write = parse_validate_and_update_catalog(
+                    if !replay_db(&write.db_name) {
+                        skipped_writes += 1;
+                        continue;
+                    }
+                    let validated_write = parse_validate_and_update_catalog(
                         NamespaceName::new(write.db_name.clone())?,
                         &write.lp,
                         catalog,
                         Time::from_timestamp_nanos(write.default_time),
                         segment_duration,
+                        true,
                         false,
                         write.precision,
-                    )?;
+                        write.source.as_deref(),
+                        None,
+                    );
+                    let mut validated_write = match validated_write {
+                        Ok(validated_write) => validated_write,
+                        Err(e) => {
+                            warn!(db_name = %write.db_name, error = %e, "quarantining wal write");
+                            quarantined_writes.push(QuarantinedWrite {
+                                db_name: write.db_name.clone(),
+                                lp: write.lp.clone(),
+                                error: e.to_string(),
+                            });
+                            continue;
+                        }
+                    };
+                    for line_error in &validated_write.errors {
+                        warn!(
+                            db_name = %write.db_name,
+                            error = %line_error.error_message,
+                            "quarantining wal line"
+                        );
+                        quarantined_writes.push(QuarantinedWrite {
+                            db_name: write.db_name.clone(),
+                            lp: line_error.original_line.clone(),
+                            error: line_error.error_message.clone(),
+                        });
+                    }
 
                     let db_name = &write.db_name;
                     if !buffered_data.database_buffers.contains_key(db_name) {
@@ -214,12 +278,15 @@ pub(crate) fn load_buffer_from_segment(
 
                     // there should only ever be data for a single segment as this is all read
                     // from one segment file
-                    if validated_write.valid_segmented_data.len() != 1 {
+                    if validated_write.valid_segmented_data.len() > 1 {
                         return Err(Error::WalOpForMultipleSegments(
                             segment_reader.path().to_string(),
                         ));
                     }
-                    let segment_data = validated_write.valid_segmented_data.pop().unwrap();
+                    // every line of the write may have been quarantined
+                    let Some(segment_data) = validated_write.valid_segmented_data.pop() else {
+                        continue;
+                    };
 
                     let schema = catalog
                         .db_schema(db_name)
@@ -241,6 +308,13 @@ pub(crate) fn load_buffer_from_segment(
         }
     }
 
+    if skipped_writes > 0 {
+        warn!(
+            segment_id = segment_reader.header().id.0,
+            skipped_writes, "skipped replay of wal writes by database replay mode, they are lost"
+        );
+    }
+
     Ok((buffered_data, segment_size))
 }
 
@@ -285,6 +359,13 @@ pub struct BufferedData {
 }
 
 impl BufferedData {
+    /// Returns the buffer for the given table, if it has data
+    pub(crate) fn table_buffer(&self, db_name: &str, table_name: &str) -> Option<&TableBuffer> {
+        self.database_buffers
+            .get(db_name)
+            .and_then(|db_buffer| db_buffer.table_buffers.get(table_name))
+    }
+
     /// Returns the table data as record batches
     pub(crate) fn table_record_batches(
         &self,
@@ -293,9 +374,7 @@ impl BufferedData {
         schema: SchemaRef,
         filter: &[Expr],
     ) -> Option<TableBufferResult<RecordBatch>> {
-        self.database_buffers
-            .get(db_name)
-            .and_then(|db_buffer| db_buffer.table_buffers.get(table_name))
+        self.table_buffer(db_name, table_name)
             .map(|table_buffer| table_buffer.record_batch(schema, filter))
     }
 
@@ -367,6 +446,7 @@ pub struct ClosedBufferSegment {
     pub catalog_end_sequence_number: SequenceNumber,
     pub buffered_data: BufferedData,
     pub segment_wal_bytes: u64,
+    pub late_data: bool,
     catalog: Arc<Catalog>,
 }
 
@@ -381,6 +461,7 @@ impl ClosedBufferSegment {
         catalog_end_sequence_number: SequenceNumber,
         buffered_data: BufferedData,
         segment_wal_bytes: u64,
+        late_data: bool,
         catalog: Arc<Catalog>,
     ) -> Self {
         Self {
@@ -391,6 +472,7 @@ impl ClosedBufferSegment {
             catalog_end_sequence_number,
             buffered_data,
             segment_wal_bytes,
+            late_data,
             catalog,
         }
     }
@@ -459,6 +541,7 @@ impl ClosedBufferSegment {
                                 &self.segment_key,
                             ),
                             sort_key: None,
+                            may_contain_pk_duplicates: table_buffer.may_contain_pk_duplicates(),
                             id: ChunkId::new(),
                             chunk_order: ChunkOrder::new(
                                 chunks
@@ -473,7 +556,7 @@ impl ClosedBufferSegment {
                         let sort_key = match sort_key.as_ref() {
                             Some(key) => key.clone(),
                             // Default to using tags sorted in lexographical
-                            // order as the sort key
+                            // order followed by time as the sort key
                             None => {
                                 let mut tags = table_buffer
                                     .data
@@ -483,10 +566,16 @@ impl ClosedBufferSegment {
                                     .cloned()
                                     .collect::<Vec<String>>();
                                 tags.sort();
+                                tags.push(TIME_COLUMN_NAME.to_string());
                                 SortKey::from(tags)
                             }
                         };
 
+                        // Record the sort key so that chunks for these files can be
+                        // merged by the planner instead of re-sorted
+                        table_parquet_files.sort_key =
+                            sort_key.to_columns().map(ToString::to_string).collect();
+
                         let logical_plan = ReorgPlanner::new()
                             .compact_plan(
                                 Arc::from(table_name.clone()),
@@ -507,18 +596,22 @@ impl ClosedBufferSegment {
                         // stream since we needed the row count for
                         // `ParquetFile` below
                         let row_count = data.iter().map(|b| b.num_rows()).sum::<usize>();
+                        let column_stats = column_stats(&data);
+                        let mut column_sizes = arrow_column_sizes(&data);
 
                         let batch_stream = stream_from_batches(table.schema().as_arrow(), data);
-                        let parquet_file_path = ParquetFilePath::new_with_partition_key(
+                        let parquet_file_path = parquet_file_path(
+                            persister.parquet_layout(),
                             db_name,
                             &table.name,
-                            &table_buffer.segment_key.to_string(),
-                            self.segment_id.0,
+                            self.segment_range,
+                            self.segment_id,
                         );
                         let path = parquet_file_path.to_string();
-                        let (size_bytes, meta) = persister
+                        let (size_bytes, meta, checksum) = persister
                             .persist_parquet_file(parquet_file_path, batch_stream)
                             .await?;
+                        add_parquet_column_sizes(&mut column_sizes, &meta);
 
                         let parquet_file = ParquetFile {
                             path,
@@ -526,6 +619,9 @@ impl ClosedBufferSegment {
                             row_count: row_count as u64,
                             min_time: time_min_max.min,
                             max_time: time_min_max.max,
+                            checksum: Some(checksum),
+                            column_stats,
+                            column_sizes,
                         };
                         table_parquet_files.parquet_files.push(parquet_file);
 
@@ -556,6 +652,8 @@ impl ClosedBufferSegment {
             segment_min_time,
             segment_max_time,
             databases: persisted_database_files,
+            late_data: self.late_data,
+            fencing_epoch: 0,
         };
 
         persister.persist_segment(&persisted_segment).await?;
@@ -564,18 +662,318 @@ impl ClosedBufferSegment {
     }
 }
 
+/// Merges the late arriving data of `late_segment` into `persisted_segment`, which was persisted
+/// earlier for the same time range. Tables that have files in both segments are rewritten to a
+/// single deduplicated parquet file, in which rows of the late segment replace earlier rows with
+/// the same tags and time. The merged segment keeps the id of `persisted_segment`. Returns it along
+/// with the paths of the parquet files it no longer references.
+pub(crate) async fn merge_late_data_segment<P>(
+    persisted_segment: &PersistedSegment,
+    late_segment: &PersistedSegment,
+    persister: Arc<P>,
+    executor: Arc<iox_query::exec::Executor>,
+    catalog: &Catalog,
+    segment_duration: SegmentDuration,
+) -> Result<(PersistedSegment, Vec<String>)>
+where
+    P: Persister,
+    write_buffer::Error: From<<P as Persister>::Error>,
+{
+    let segment_range = SegmentRange::from_time_and_duration(
+        Time::from_timestamp_nanos(persisted_segment.segment_min_time),
+        segment_duration,
+        false,
+    );
+    let segment_key = PartitionKey::from(segment_range.key());
+
+    let mut databases = persisted_segment.databases.clone();
+    let mut replaced_files = vec![];
+
+    for (db_name, late_tables) in &late_segment.databases {
+        let database_tables = databases.entry(db_name.clone()).or_default();
+        let db_schema = catalog.db_schema(db_name);
+
+        for (table_name, late_table) in &late_tables.tables {
+            let table = db_schema
+                .as_ref()
+                .and_then(|db_schema| db_schema.get_table(table_name));
+            let (Some(table), Some(persisted_table)) =
+                (table, database_tables.tables.get(table_name).cloned())
+            else {
+                database_tables
+                    .tables
+                    .insert(table_name.clone(), late_table.clone());
+                continue;
+            };
+
+            // files of the late segment come last, so their rows win when deduplicating
+            let mut chunks: Vec<Arc<dyn QueryChunk>> = vec![];
+            let files = persisted_table
+                .parquet_files
+                .iter()
+                .chain(&late_table.parquet_files);
+            for file in files {
+                let bytes = persister
+                    .load_parquet_file(ParquetFilePath::from(file.path.as_str()))
+                    .await?;
+                let batches = ParquetRecordBatchReaderBuilder::try_new(bytes)
+                    .and_then(|builder| builder.build())
+                    .map_err(persister::Error::from)?
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| persister::Error::from(DataFusionError::from(e)))?;
+                let Some(schema) = batches.first().map(|batch| batch.schema()) else {
+                    continue;
+                };
+                let schema = Schema::try_from(schema)
+                    .map_err(|e| Error::BufferSegmentError(e.to_string()))?;
+                let chunk_stats = create_chunk_statistics(
+                    Some(file.row_count as usize),
+                    &schema,
+                    Some(TimestampMinMax {
+                        min: file.min_time,
+                        max: file.max_time,
+                    }),
+                    None,
+                );
+
+                chunks.push(Arc::new(BufferChunk {
+                    batches,
+                    schema,
+                    stats: Arc::new(chunk_stats),
+                    partition_id: TransitionPartitionId::new(TableId::new(0), &segment_key),
+                    sort_key: None,
+                    may_contain_pk_duplicates: false,
+                    id: ChunkId::new(),
+                    chunk_order: ChunkOrder::new(
+                        chunks
+                            .len()
+                            .try_into()
+                            .expect("should never have this many chunks"),
+                    ),
+                }));
+            }
+
+            let sort_key = if persisted_table.sort_key.is_empty() {
+                SortKey::from_columns(table.schema().primary_key())
+            } else {
+                SortKey::from_columns(persisted_table.sort_key.iter().map(String::as_str))
+            };
+
+            let ctx = executor.new_context();
+            let logical_plan = ReorgPlanner::new()
+                .compact_plan(
+                    Arc::from(table_name.as_str()),
+                    table.schema(),
+                    chunks,
+                    sort_key.clone(),
+                )
+                .map_err(|e| Error::BufferSegmentError(e.to_string()))?;
+            let physical_plan = ctx
+                .create_physical_plan(&logical_plan)
+                .await
+                .map_err(persister::Error::from)?;
+            let data = ctx
+                .collect(physical_plan)
+                .await
+                .map_err(persister::Error::from)?;
+
+            let row_count = data.iter().map(|b| b.num_rows()).sum::<usize>();
+            let column_stats = column_stats(&data);
+            let mut column_sizes = arrow_column_sizes(&data);
+            let batch_stream = stream_from_batches(table.schema().as_arrow(), data);
+            let parquet_file_path = parquet_file_path(
+                persister.parquet_layout(),
+                db_name,
+                table_name,
+                segment_range,
+                persisted_segment.segment_id,
+            )
+            .merged_with(late_segment.segment_id);
+            let path = parquet_file_path.to_string();
+            let (size_bytes, meta, checksum) = persister
+                .persist_parquet_file(parquet_file_path, batch_stream)
+                .await?;
+            add_parquet_column_sizes(&mut column_sizes, &meta);
+
+            let replaced = persisted_table
+                .parquet_files
+                .iter()
+                .chain(&late_table.parquet_files)
+                .collect::<Vec<_>>();
+            let min_time = replaced.iter().map(|file| file.min_time).min();
+            let max_time = replaced.iter().map(|file| file.max_time).max();
+            let parquet_file = ParquetFile {
+                path,
+                size_bytes,
+                row_count: row_count as u64,
+                min_time: min_time.unwrap_or_default(),
+                max_time: max_time.unwrap_or_default(),
+                checksum: Some(checksum),
+                column_stats,
+                column_sizes,
+            };
+            replaced_files.extend(replaced.into_iter().map(|file| file.path.clone()));
+
+            database_tables.tables.insert(
+                table_name.clone(),
+                TableParquetFiles {
+                    table_name: table_name.clone(),
+                    parquet_files: vec![parquet_file],
+                    sort_key: sort_key.to_columns().map(ToString::to_string).collect(),
+                },
+            );
+        }
+    }
+
+    let parquet_files = databases
+        .values()
+        .flat_map(|db| db.tables.values())
+        .flat_map(|table| &table.parquet_files);
+    let merged_segment = PersistedSegment {
+        segment_id: persisted_segment.segment_id,
+        segment_wal_size_bytes: persisted_segment.segment_wal_size_bytes
+            + late_segment.segment_wal_size_bytes,
+        segment_parquet_size_bytes: parquet_files.clone().map(|file| file.size_bytes).sum(),
+        segment_row_count: parquet_files.clone().map(|file| file.row_count).sum(),
+        segment_min_time: persisted_segment
+            .segment_min_time
+            .min(late_segment.segment_min_time),
+        segment_max_time: persisted_segment
+            .segment_max_time
+            .max(late_segment.segment_max_time),
+        databases,
+        late_data: false,
+        fencing_epoch: 0,
+    };
+
+    persister.persist_segment(&merged_segment).await?;
+
+    Ok((merged_segment, replaced_files))
+}
+
+/// Returns the path that the parquet file of a table is persisted to for the given segment,
+/// following the configured [`ParquetLayout`].
+fn parquet_file_path(
+    layout: ParquetLayout,
+    db_name: &str,
+    table_name: &str,
+    segment_range: SegmentRange,
+    segment_id: SegmentId,
+) -> ParquetFilePath {
+    match layout {
+        ParquetLayout::Segment => ParquetFilePath::new_with_partition_key(
+            db_name,
+            table_name,
+            &segment_range.key(),
+            segment_id.0,
+        ),
+        ParquetLayout::Template(template) => ParquetFilePath::new_from_template(
+            &template,
+            db_name,
+            table_name,
+            segment_range.start_time.date_time(),
+            segment_id.0,
+        ),
+    }
+}
+
+/// Computes the statistics for every non-time column in the batches. Columns whose values
+/// can't be summarized are left out, which the query planner treats as unknown.
+fn column_stats(batches: &[RecordBatch]) -> BTreeMap<String, ColumnStats> {
+    let Some(schema) = batches.first().map(|b| b.schema()) else {
+        return BTreeMap::new();
+    };
+
+    let mut stats = BTreeMap::new();
+    for (i, field) in schema.fields().iter().enumerate() {
+        if field.name() == TIME_COLUMN_NAME {
+            continue;
+        }
+
+        // tags are dictionary encoded, their stats are kept as plain strings
+        let data_type = match field.data_type() {
+            DataType::Dictionary(_, value_type) => value_type.as_ref().clone(),
+            data_type => data_type.clone(),
+        };
+
+        let (Ok(mut min), Ok(mut max)) = (
+            MinAccumulator::try_new(&data_type),
+            MaxAccumulator::try_new(&data_type),
+        ) else {
+            continue;
+        };
+
+        let mut null_count = 0;
+        let mut summarized = true;
+        for batch in batches {
+            let column = batch.column(i);
+            null_count += column.null_count() as u64;
+            let Ok(column) = cast(column, &data_type) else {
+                summarized = false;
+                break;
+            };
+            let values = [column];
+            if min.update_batch(&values).is_err() || max.update_batch(&values).is_err() {
+                summarized = false;
+                break;
+            }
+        }
+
+        if let (true, Ok(min), Ok(max)) = (summarized, min.evaluate(), max.evaluate()) {
+            stats.insert(
+                field.name().to_string(),
+                ColumnStats {
+                    min: StatValue::from_scalar(&min),
+                    max: StatValue::from_scalar(&max),
+                    null_count,
+                },
+            );
+        }
+    }
+
+    stats
+}
+
+/// Sums the memory used by each column's arrays across the batches
+fn arrow_column_sizes(batches: &[RecordBatch]) -> BTreeMap<String, ColumnSize> {
+    let mut sizes = BTreeMap::<String, ColumnSize>::new();
+    for batch in batches {
+        for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+            sizes
+                .entry(field.name().to_string())
+                .or_default()
+                .arrow_bytes += column.get_array_memory_size() as u64;
+        }
+    }
+    sizes
+}
+
+/// Adds the compressed size of each column's chunks in the written file
+fn add_parquet_column_sizes(sizes: &mut BTreeMap<String, ColumnSize>, meta: &FileMetaData) {
+    let column_chunks = meta
+        .row_groups
+        .iter()
+        .flat_map(|row_group| &row_group.columns)
+        .filter_map(|column| column.meta_data.as_ref());
+    for column_meta in column_chunks {
+        if let Some(name) = column_meta.path_in_schema.first() {
+            sizes.entry(name.clone()).or_default().parquet_bytes +=
+                column_meta.total_compressed_size as u64;
+        }
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
     use crate::test_helpers::{lp_to_table_batches, lp_to_write_batch};
     use crate::wal::WalSegmentWriterNoopImpl;
-    use crate::{persister, LpWriteOp, PersistedCatalog};
+    use crate::{LpWriteOp, PersistedCatalog};
     use arrow_util::assert_batches_eq;
     use bytes::Bytes;
     use datafusion::execution::SendableRecordBatchStream;
     use object_store::ObjectStore;
     use parking_lot::Mutex;
-    use parquet::format::FileMetaData;
     use std::any::Any;
     use std::str::FromStr;
 
@@ -745,6 +1143,7 @@ pub(crate) mod tests {
             lp: lp.to_string(),
             default_time: 0,
             precision: crate::Precision::Nanosecond,
+            source: None,
         });
 
         let write_batch = lp_to_write_batch(&catalog, "db1", lp);
@@ -794,12 +1193,35 @@ pub(crate) mod tests {
             cpu_parqet.path,
             ParquetFilePath::new_with_partition_key("db1", "cpu", SEGMENT_KEY, 4).to_string()
         );
+        assert_eq!(cpu.sort_key, vec!["tag1", "time"]);
         assert_eq!(cpu_parqet.row_count, 2);
         assert_eq!(cpu_parqet.min_time, 10);
         assert_eq!(cpu_parqet.max_time, 10);
+        assert_eq!(
+            cpu_parqet.column_stats,
+            BTreeMap::from([
+                (
+                    "bar".to_string(),
+                    ColumnStats {
+                        min: Some(StatValue::F64(1.0)),
+                        max: Some(StatValue::F64(5.0)),
+                        null_count: 0,
+                    }
+                ),
+                (
+                    "tag1".to_string(),
+                    ColumnStats {
+                        min: Some(StatValue::String("cupcakes".to_string())),
+                        max: Some(StatValue::String("something".to_string())),
+                        null_count: 0,
+                    }
+                ),
+            ])
+        );
 
         let mem = db.tables.get("mem").unwrap();
         let mem_parqet = &mem.parquet_files[0];
+        assert_eq!(mem.sort_key, vec!["tag2", "time"]);
 
         // file number of the path should match the segment id
         assert_eq!(
@@ -809,6 +1231,16 @@ pub(crate) mod tests {
         assert_eq!(mem_parqet.row_count, 2);
         assert_eq!(mem_parqet.min_time, 15);
         assert_eq!(mem_parqet.max_time, 20);
+
+        // the test persister writes no row groups, so only the arrow sizes are known
+        assert_eq!(
+            mem_parqet.column_sizes.keys().collect::<Vec<_>>(),
+            vec!["bar", "tag2", "time"]
+        );
+        assert!(mem_parqet
+            .column_sizes
+            .values()
+            .all(|size| size.arrow_bytes > 0 && size.parquet_bytes == 0));
     }
 
     #[test]
@@ -921,10 +1353,10 @@ pub(crate) mod tests {
             &self,
             path: ParquetFilePath,
             _data: SendableRecordBatchStream,
-        ) -> persister::Result<(u64, FileMetaData)> {
+        ) -> persister::Result<(u64, FileMetaData, String)> {
             self.state.lock().parquet_files.push(path);
             let meta = FileMetaData::new(1, vec![], 1, vec![], None, None, None, None, None);
-            Ok((1, meta))
+            Ok((1, meta, persister::parquet_checksum(&[])))
         }
 
         async fn persist_segment(&self, segment: &PersistedSegment) -> persister::Result<()> {
@@ -932,6 +1364,27 @@ pub(crate) mod tests {
             Ok(())
         }
 
+        async fn delete_segment(&self, segment_id: SegmentId) -> persister::Result<()> {
+            self.state
+                .lock()
+                .segments
+                .retain(|segment| segment.segment_id != segment_id);
+            Ok(())
+        }
+
+        async fn delete_parquet_file(&self, path: ParquetFilePath) -> persister::Result<()> {
+            self.state.lock().parquet_files.retain(|file| *file != path);
+            Ok(())
+        }
+
+        async fn persist_quarantined_writes(
+            &self,
+            _segment_id: SegmentId,
+            _writes: &[QuarantinedWrite],
+        ) -> persister::Result<()> {
+            todo!()
+        }
+
         fn as_any(&self) -> &dyn Any {
             self as &dyn Any
         }
@@ -272,7 +272,10 @@ mod tests {
             ingest_time,
             SegmentDuration::new_5m(),
             false,
+            true,
             Precision::Nanosecond,
+            None,
+            None,
         )
         .unwrap();
 
@@ -288,7 +291,10 @@ mod tests {
             ingest_time,
             SegmentDuration::new_5m(),
             false,
+            true,
             Precision::Nanosecond,
+            None,
+            None,
         )
         .unwrap();
         flusher
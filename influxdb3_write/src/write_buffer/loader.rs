@@ -8,8 +8,9 @@ use crate::write_buffer::{
     Result,
 };
 use crate::{persister, write_buffer, PersistedCatalog, PersistedSegment, Persister, SegmentId};
-use crate::{SegmentDuration, SegmentRange, Wal};
+use crate::{ReplayMode, SegmentDuration, SegmentRange, Wal};
 use iox_time::Time;
+use observability_deps::tracing::warn;
 use std::sync::Arc;
 
 const SEGMENTS_TO_LOAD: usize = 1000;
@@ -24,11 +25,15 @@ pub struct LoadedState {
     pub last_segment_id: SegmentId,
 }
 
+/// Loads the persisted state and replays the WAL. Each database's writes are replayed by the
+/// [`ReplayMode`] set for it in the catalog, unless `replay_mode` overrides it for every
+/// database.
 pub async fn load_starting_state<P, W>(
     persister: Arc<P>,
     wal: Option<Arc<W>>,
     server_load_time: Time,
     segment_duration: SegmentDuration,
+    replay_mode: Option<ReplayMode>,
 ) -> Result<LoadedState>
 where
     P: Persister,
@@ -72,7 +77,47 @@ where
             let starting_sequence_number = catalog.sequence_number();
             let segment_reader = wal.open_segment_reader(segment_file.segment_id)?;
             let segment_header = *segment_reader.header();
-            let buffer = load_buffer_from_segment(&catalog, segment_reader)?;
+
+            // a segment left out of replay for every database is deleted from the wal, while the
+            // writes of databases left out by their own replay mode are only skipped, and lost
+            // when the segment is persisted
+            if let Some(replay_mode) = replay_mode {
+                if !replay_mode.replays(segment_header.range, server_load_time) {
+                    warn!(
+                        segment_id = segment_header.id.0,
+                        %replay_mode,
+                        "skipping replay of wal segment, its writes are lost"
+                    );
+                    drop(segment_reader);
+                    wal.delete_wal_segment(segment_header.id)?;
+                    continue;
+                }
+            }
+            let replay_db = |db_name: &str| {
+                replay_mode.is_some()
+                    || catalog
+                        .db_schema(db_name)
+                        .and_then(|db| db.replay_mode())
+                        .unwrap_or_default()
+                        .replays(segment_header.range, server_load_time)
+            };
+            let mut quarantined_writes = vec![];
+            let buffer = load_buffer_from_segment(
+                &catalog,
+                segment_reader,
+                &mut quarantined_writes,
+                replay_db,
+            )?;
+            if !quarantined_writes.is_empty() {
+                warn!(
+                    segment_id = segment_header.id.0,
+                    count = quarantined_writes.len(),
+                    "wal writes failed validation on replay and were quarantined"
+                );
+                persister
+                    .persist_quarantined_writes(segment_header.id, &quarantined_writes)
+                    .await?;
+            }
 
             let segment = OpenBufferSegment::new(
                 Arc::clone(&catalog),
@@ -142,20 +187,23 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::catalog::{FieldRule, FieldValidation, ValidationAction};
+    use crate::paths::QuarantineFilePath;
     use crate::persister::PersisterImpl;
     use crate::test_helpers::lp_to_write_batch;
     use crate::wal::{WalImpl, WalSegmentWriterNoopImpl};
     use crate::Precision;
     use crate::{
-        DatabaseTables, LpWriteOp, ParquetFile, SegmentRange, SequenceNumber, TableParquetFiles,
-        WalOp,
+        ColumnStats, DatabaseTables, LpWriteOp, ParquetFile, QuarantinedWrite, SegmentRange,
+        SequenceNumber, StatValue, TableParquetFiles, WalOp,
     };
     use arrow_util::assert_batches_eq;
     use iox_time::Time;
     use object_store::memory::InMemory;
     use object_store::ObjectStore;
     use pretty_assertions::assert_eq;
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
+    use std::time::Duration;
 
     #[tokio::test]
     async fn loads_without_wal() {
@@ -182,6 +230,7 @@ mod tests {
             lp: lp.to_string(),
             default_time: 0,
             precision: Precision::Nanosecond,
+            source: None,
         });
 
         let write_batch = lp_to_write_batch(&catalog, "db1", lp);
@@ -201,6 +250,7 @@ mod tests {
             None::<Arc<crate::wal::WalImpl>>,
             Time::from_timestamp_nanos(0),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
@@ -257,6 +307,7 @@ mod tests {
             Some(Arc::clone(&wal)),
             Time::from_timestamp_nanos(0),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
@@ -270,6 +321,7 @@ mod tests {
             lp: lp.to_string(),
             default_time: 0,
             precision: Precision::Nanosecond,
+            source: None,
         });
 
         let write_batch = lp_to_write_batch(&catalog, db_name, lp);
@@ -282,6 +334,7 @@ mod tests {
             Some(wal),
             Time::from_timestamp_nanos(0),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
@@ -326,6 +379,269 @@ mod tests {
         assert_eq!(loaded_state.last_segment_id, SegmentId::new(1));
     }
 
+    #[tokio::test]
+    async fn replay_mode_skips_old_segments() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let dir = test_helpers::tmp_dir().unwrap().into_path();
+        let wal = Arc::new(WalImpl::new(dir.clone()).unwrap());
+        let db_name = "db1";
+
+        let LoadedState {
+            catalog,
+            mut open_segments,
+            ..
+        } = load_starting_state(
+            Arc::clone(&persister),
+            Some(Arc::clone(&wal)),
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let mut current_segment = open_segments.pop().unwrap();
+        let old_segment_id = current_segment.segment_id();
+        let lp = "cpu,tag1=cupcakes bar=1 10";
+        let wal_op = WalOp::LpWrite(LpWriteOp {
+            db_name: db_name.to_string(),
+            lp: lp.to_string(),
+            default_time: 0,
+            precision: Precision::Nanosecond,
+            source: None,
+        });
+        let write_batch = lp_to_write_batch(&catalog, db_name, lp);
+        current_segment.write_wal_ops(vec![wal_op]).unwrap();
+        current_segment.buffer_writes(write_batch).unwrap();
+        drop(current_segment);
+
+        // the segment ended 15 minutes before this load, so it's replayed with a 30 minute window
+        let server_load_time = Time::from_timestamp(20 * 60, 0).unwrap();
+        let loaded_state = load_starting_state(
+            Arc::clone(&persister),
+            Some(Arc::clone(&wal)),
+            server_load_time,
+            SegmentDuration::new_5m(),
+            Some(ReplayMode::Since(Duration::from_secs(30 * 60))),
+        )
+        .await
+        .unwrap();
+        assert_eq!(loaded_state.persisting_buffer_segments.len(), 1);
+        assert!(loaded_state.catalog.db_schema(db_name).is_some());
+        drop(loaded_state);
+
+        // but not with a 10 minute window, which deletes it from the wal
+        let loaded_state = load_starting_state(
+            persister,
+            Some(Arc::clone(&wal)),
+            server_load_time,
+            SegmentDuration::new_5m(),
+            Some(ReplayMode::Since(Duration::from_secs(10 * 60))),
+        )
+        .await
+        .unwrap();
+        assert!(loaded_state.persisting_buffer_segments.is_empty());
+        assert!(loaded_state.catalog.db_schema(db_name).is_none());
+        assert!(wal
+            .segment_files()
+            .unwrap()
+            .iter()
+            .all(|f| f.segment_id != old_segment_id));
+    }
+
+    #[tokio::test]
+    async fn replay_mode_is_set_per_database() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let dir = test_helpers::tmp_dir().unwrap().into_path();
+        let wal = Arc::new(WalImpl::new(dir.clone()).unwrap());
+
+        let catalog = Catalog::new();
+        catalog
+            .set_replay_mode("skipped", Some(ReplayMode::Skip))
+            .unwrap();
+        persister
+            .persist_catalog(
+                SegmentId::new(0),
+                Catalog::from_inner(catalog.clone_inner()),
+            )
+            .await
+            .unwrap();
+
+        let LoadedState {
+            mut open_segments, ..
+        } = load_starting_state(
+            Arc::clone(&persister),
+            Some(Arc::clone(&wal)),
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            None,
+        )
+        .await
+        .unwrap();
+        let mut current_segment = open_segments.pop().unwrap();
+        let wal_ops = ["skipped", "replayed"]
+            .into_iter()
+            .map(|db_name| {
+                WalOp::LpWrite(LpWriteOp {
+                    db_name: db_name.to_string(),
+                    lp: "cpu,tag1=cupcakes bar=1 10".to_string(),
+                    default_time: 0,
+                    precision: Precision::Nanosecond,
+                    source: None,
+                })
+            })
+            .collect();
+        current_segment.write_wal_ops(wal_ops).unwrap();
+        drop(current_segment);
+
+        // only the database without a replay mode of skip has its writes replayed
+        let loaded_state = load_starting_state(
+            Arc::clone(&persister),
+            Some(Arc::clone(&wal)),
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            None,
+        )
+        .await
+        .unwrap();
+        let schema = |db_name| {
+            loaded_state
+                .catalog
+                .db_schema(db_name)
+                .and_then(|db| db.get_table("cpu").cloned())
+        };
+        assert!(schema("skipped").is_none());
+        let cpu_table = schema("replayed").unwrap();
+        assert!(loaded_state.open_segments[0]
+            .table_record_batch("replayed", "cpu", cpu_table.schema().as_arrow(), &[])
+            .is_some());
+        drop(loaded_state);
+
+        // a replay mode given at startup overrides the database's own
+        let loaded_state = load_starting_state(
+            persister,
+            Some(wal),
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            Some(ReplayMode::Full),
+        )
+        .await
+        .unwrap();
+        let cpu_table = loaded_state
+            .catalog
+            .db_schema("skipped")
+            .unwrap()
+            .get_table("cpu")
+            .cloned()
+            .unwrap();
+        assert!(loaded_state.open_segments[0]
+            .table_record_batch("skipped", "cpu", cpu_table.schema().as_arrow(), &[])
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn replays_wal_writes_without_applying_field_rules() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let dir = test_helpers::tmp_dir().unwrap().into_path();
+        let wal = Arc::new(WalImpl::new(dir.clone()).unwrap());
+        let db_name = "db1";
+
+        // a rule added after the write was made to the wal would reject one of its lines
+        let catalog = Catalog::new();
+        lp_to_write_batch(&catalog, db_name, "cpu,tag1=cupcakes bar=1 1");
+        catalog
+            .set_field_validation(
+                db_name,
+                "cpu",
+                "bar",
+                Some(FieldValidation {
+                    rule: FieldRule::Range {
+                        min: Some(0.0),
+                        max: Some(100.0),
+                    },
+                    action: ValidationAction::Reject,
+                }),
+            )
+            .unwrap();
+        persister
+            .persist_catalog(
+                SegmentId::new(0),
+                Catalog::from_inner(catalog.clone_inner()),
+            )
+            .await
+            .unwrap();
+
+        let LoadedState {
+            mut open_segments, ..
+        } = load_starting_state(
+            Arc::clone(&persister),
+            Some(Arc::clone(&wal)),
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            None,
+        )
+        .await
+        .unwrap();
+        let mut current_segment = open_segments.pop().unwrap();
+        let segment_id = current_segment.segment_id();
+        current_segment
+            .write_wal_ops(vec![WalOp::LpWrite(LpWriteOp {
+                db_name: db_name.to_string(),
+                lp: "cpu,tag1=cupcakes bar=500 10\n\
+                     cpu,tag1=cupcakes bar=2 20\n\
+                     cpu,tag1=cupcakes bar=\"three\" 30"
+                    .to_string(),
+                default_time: 0,
+                precision: Precision::Nanosecond,
+                source: None,
+            })])
+            .unwrap();
+
+        let loaded_state = load_starting_state(
+            Arc::clone(&persister),
+            Some(wal),
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // but the rules were applied when the write was made, so the lines with float values are
+        // replayed
+        let db = loaded_state.catalog.db_schema(db_name).unwrap();
+        let cpu_table = db.get_table("cpu").unwrap();
+        let cpu_data = loaded_state.open_segments[0]
+            .table_record_batch(db_name, "cpu", cpu_table.schema().as_arrow(), &[])
+            .unwrap()
+            .unwrap();
+        let expected = [
+            "+-------+----------+--------------------------------+",
+            "| bar   | tag1     | time                           |",
+            "+-------+----------+--------------------------------+",
+            "| 500.0 | cupcakes | 1970-01-01T00:00:00.000000010Z |",
+            "| 2.0   | cupcakes | 1970-01-01T00:00:00.000000020Z |",
+            "+-------+----------+--------------------------------+",
+        ];
+        assert_batches_eq!(&expected, &[cpu_data]);
+
+        // and only the line that no longer matches the schema is kept in object storage
+        let bytes = object_store
+            .get(&QuarantineFilePath::new_wal_segment(segment_id))
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let quarantined: Vec<QuarantinedWrite> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].db_name, db_name);
+        assert!(quarantined[0].lp.contains("bar=\"three\""));
+    }
+
     #[tokio::test]
     async fn loads_with_persisted_segments_and_wal() {
         let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
@@ -343,6 +659,7 @@ mod tests {
             Some(Arc::clone(&wal)),
             Time::from_timestamp_nanos(0),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
@@ -356,6 +673,7 @@ mod tests {
             lp: lp.to_string(),
             default_time: 0,
             precision: Precision::Nanosecond,
+            source: None,
         });
 
         let write_batch = lp_to_write_batch(&catalog, db_name, lp);
@@ -384,6 +702,7 @@ mod tests {
             lp: lp.to_string(),
             default_time: 0,
             precision: Precision::Nanosecond,
+            source: None,
         });
 
         let write_batch = lp_to_write_batch(&catalog, db_name, lp);
@@ -410,6 +729,7 @@ mod tests {
             Some(wal),
             Time::from_timestamp(6 * 60, 0).unwrap(),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
@@ -417,9 +737,23 @@ mod tests {
         // verify that the persisted segment doesn't show up as one that should be persisting
         assert!(loaded_state.persisting_buffer_segments.is_empty());
 
+        // checksums and column sizes depend on the parquet encoding, so only check that they
+        // were recorded
+        let mut persisted_segment = loaded_state.persisted_segments[0].clone();
+        for table in persisted_segment
+            .databases
+            .values_mut()
+            .flat_map(|db| db.tables.values_mut())
+        {
+            for file in &mut table.parquet_files {
+                assert!(file.checksum.take().is_some());
+                assert!(!std::mem::take(&mut file.column_sizes).is_empty());
+            }
+        }
+
         // verify the data was persisted
         assert_eq!(
-            loaded_state.persisted_segments[0],
+            persisted_segment,
             PersistedSegment {
                 segment_id,
                 segment_wal_size_bytes: 252,
@@ -427,6 +761,8 @@ mod tests {
                 segment_row_count: 3,
                 segment_min_time: 10,
                 segment_max_time: 20,
+                late_data: false,
+                fencing_epoch: 0,
                 databases: HashMap::from([(
                     "db1".to_string(),
                     DatabaseTables {
@@ -442,8 +778,32 @@ mod tests {
                                         row_count: 1,
                                         min_time: 10,
                                         max_time: 10,
+                                        checksum: None,
+                                        column_sizes: BTreeMap::new(),
+                                        column_stats: BTreeMap::from([
+                                            (
+                                                "bar".to_string(),
+                                                ColumnStats {
+                                                    min: Some(StatValue::F64(1.0)),
+                                                    max: Some(StatValue::F64(1.0)),
+                                                    null_count: 0,
+                                                }
+                                            ),
+                                            (
+                                                "tag1".to_string(),
+                                                ColumnStats {
+                                                    min: Some(StatValue::String(
+                                                        "cupcakes".to_string()
+                                                    )),
+                                                    max: Some(StatValue::String(
+                                                        "cupcakes".to_string()
+                                                    )),
+                                                    null_count: 0,
+                                                }
+                                            ),
+                                        ]),
                                     }],
-                                    sort_key: vec![],
+                                    sort_key: vec!["tag1".to_string(), "time".to_string()],
                                 }
                             ),
                             (
@@ -457,8 +817,32 @@ mod tests {
                                         row_count: 2,
                                         min_time: 15,
                                         max_time: 20,
+                                        checksum: None,
+                                        column_sizes: BTreeMap::new(),
+                                        column_stats: BTreeMap::from([
+                                            (
+                                                "bar".to_string(),
+                                                ColumnStats {
+                                                    min: Some(StatValue::F64(2.0)),
+                                                    max: Some(StatValue::F64(3.0)),
+                                                    null_count: 0,
+                                                }
+                                            ),
+                                            (
+                                                "tag2".to_string(),
+                                                ColumnStats {
+                                                    min: Some(StatValue::String(
+                                                        "snakes".to_string()
+                                                    )),
+                                                    max: Some(StatValue::String(
+                                                        "turtles".to_string()
+                                                    )),
+                                                    null_count: 0,
+                                                }
+                                            ),
+                                        ]),
                                     }],
-                                    sort_key: vec![],
+                                    sort_key: vec!["tag2".to_string(), "time".to_string()],
                                 }
                             )
                         ])
@@ -520,6 +904,7 @@ mod tests {
             Some(Arc::clone(&wal)),
             Time::from_timestamp_nanos(0),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
@@ -532,6 +917,7 @@ mod tests {
             lp: lp.to_string(),
             default_time: 0,
             precision: Precision::Nanosecond,
+            source: None,
         });
 
         let write_batch = lp_to_write_batch(&catalog, db_name, lp);
@@ -553,6 +939,7 @@ mod tests {
             lp: lp.to_string(),
             default_time: 0,
             precision: Precision::Nanosecond,
+            source: None,
         });
 
         let write_batch = lp_to_write_batch(&catalog, db_name, lp);
@@ -581,6 +968,7 @@ mod tests {
             Some(wal),
             Time::from_timestamp(6 * 60, 0).unwrap(),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
@@ -652,6 +1040,7 @@ mod tests {
             Some(Arc::clone(&wal)),
             Time::from_timestamp_nanos(0),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
@@ -665,6 +1054,7 @@ mod tests {
             Some(wal),
             Time::from_timestamp(360, 0).unwrap(),
             SegmentDuration::new_5m(),
+            None,
         )
         .await
         .unwrap();
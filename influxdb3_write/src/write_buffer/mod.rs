@@ -7,38 +7,50 @@ mod segment_state;
 mod table_buffer;
 
 use crate::cache::ParquetCache;
-use crate::catalog::{Catalog, DatabaseSchema, TableDefinition, TIME_COLUMN_NAME};
+use crate::catalog::{
+    Catalog, DatabaseSchema, SchemaChangeOrigin, TableDefinition, ValidationAction,
+    INGESTED_AT_COLUMN_NAME, REJECTED_WRITES_TABLE_NAME, SOURCE_COLUMN_NAME, SUSPECT_TAG_NAME,
+    TIME_COLUMN_NAME,
+};
 use crate::chunk::ParquetChunk;
-use crate::persister::PersisterImpl;
+use crate::persister::{self, PersisterImpl};
 use crate::write_buffer::flusher::WriteBufferFlusher;
 use crate::write_buffer::loader::load_starting_state;
-use crate::write_buffer::segment_state::{run_buffer_segment_persist_and_cleanup, SegmentState};
+use crate::write_buffer::segment_state::{
+    filter_time_range, run_buffer_segment_persist_and_cleanup, SegmentState,
+};
 use crate::{
-    BufferedWriteRequest, Bufferer, ChunkContainer, LpWriteOp, Persister, Precision,
-    SegmentDuration, SequenceNumber, Wal, WalOp, WriteBuffer, WriteLineError,
+    BufferedWriteRequest, Bufferer, ChunkContainer, LpWriteOp, ParquetFile, Persister, Precision,
+    ReplayMode, SegmentDuration, SegmentId, SequenceNumber, Wal, WalOp, WriteBuffer,
+    WriteLineError,
 };
 use async_trait::async_trait;
 use data_types::{
     column_type_from_field, ChunkId, ChunkOrder, ColumnType, NamespaceName, NamespaceNameError,
 };
-use datafusion::common::DataFusionError;
+use datafusion::common::{stats, DataFusionError, Statistics};
 use datafusion::execution::context::SessionState;
-use datafusion::logical_expr::Expr;
+use datafusion::logical_expr::{col, lit_timestamp_nano, Expr};
 use datafusion::physical_plan::SendableRecordBatchStream;
-use influxdb_line_protocol::{parse_lines, FieldValue, ParsedLine};
+use influxdb_line_protocol::{parse_lines, split_lines, FieldValue, ParsedLine};
 use iox_query::chunk_statistics::create_chunk_statistics;
 use iox_query::QueryChunk;
 use iox_time::{Time, TimeProvider};
+use metric::U64Gauge;
 use object_store::path::Path as ObjPath;
 use object_store::ObjectMeta;
-use observability_deps::tracing::{debug, error};
+use observability_deps::tracing::{debug, error, info, warn};
 use parking_lot::{Mutex, RwLock};
 use parquet_file::storage::ParquetExecInput;
 use sha2::Digest;
 use sha2::Sha256;
 use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
 use std::i64;
+use std::num::NonZeroU8;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock};
 use thiserror::Error;
 use tokio::sync::watch;
@@ -78,6 +90,12 @@ pub enum Error {
 
     #[error("error from table buffer: {0}")]
     TableBufferError(#[from] table_buffer::Error),
+
+    #[error(
+        "{persisting} segments are waiting to be persisted, more than the limit of {limit}, \
+        writes are rejected until persistence catches up"
+    )]
+    PersistBacklog { persisting: usize, limit: usize },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -98,12 +116,68 @@ pub struct WriteBufferImpl<W, T> {
     wal: Option<Arc<W>>,
     write_buffer_flusher: WriteBufferFlusher,
     segment_duration: SegmentDuration,
-    #[allow(dead_code)]
     time_provider: Arc<T>,
     #[allow(dead_code)]
     segment_persist_handle: Mutex<tokio::task::JoinHandle<()>>,
     #[allow(dead_code)]
     shutdown_segment_persist_tx: watch::Sender<()>,
+    persist_backlog: Option<PersistBacklog>,
+}
+
+/// Tracks whether the number of segments waiting to be persisted is over its limit. While it is,
+/// the write buffer is catching up and rejects writes, so that a persister that has fallen
+/// behind can't let buffered data grow until the server runs out of memory.
+#[derive(Debug)]
+struct PersistBacklog {
+    limit: usize,
+    catching_up: AtomicBool,
+    catching_up_gauge: U64Gauge,
+    persisting_gauge: U64Gauge,
+}
+
+impl PersistBacklog {
+    fn new(limit: usize, metrics: &metric::Registry) -> Self {
+        Self {
+            limit,
+            catching_up: AtomicBool::new(false),
+            catching_up_gauge: metrics
+                .register_metric::<U64Gauge>(
+                    "influxdb3_persist_backlog_catching_up",
+                    "Whether writes are rejected until the persistence backlog is back under its limit",
+                )
+                .recorder(&[]),
+            persisting_gauge: metrics
+                .register_metric::<U64Gauge>(
+                    "influxdb3_persist_backlog_segments",
+                    "Number of closed segments waiting to be persisted",
+                )
+                .recorder(&[]),
+        }
+    }
+
+    /// Checks the number of persisting segments against the limit, logging when the write
+    /// buffer starts or stops catching up.
+    fn check(&self, persisting: usize) -> Result<()> {
+        let limit = self.limit;
+        let catching_up = persisting > limit;
+        self.persisting_gauge.set(persisting as u64);
+        self.catching_up_gauge.set(catching_up as u64);
+        if self.catching_up.swap(catching_up, Ordering::Relaxed) != catching_up {
+            if catching_up {
+                error!(
+                    persisting,
+                    limit, "persistence backlog over limit, rejecting writes until it catches up"
+                );
+            } else {
+                info!(persisting, limit, "persistence caught up, accepting writes");
+            }
+        }
+
+        if catching_up {
+            return Err(Error::PersistBacklog { persisting, limit });
+        }
+        Ok(())
+    }
 }
 
 impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
@@ -113,12 +187,27 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
         time_provider: Arc<T>,
         segment_duration: SegmentDuration,
         executor: Arc<iox_query::exec::Executor>,
+        replay_mode: Option<ReplayMode>,
     ) -> Result<Self> {
         let now = time_provider.now();
-        let loaded_state =
-            load_starting_state(Arc::clone(&persister), wal.clone(), now, segment_duration).await?;
+        let loaded_state = load_starting_state(
+            Arc::clone(&persister),
+            wal.clone(),
+            now,
+            segment_duration,
+            replay_mode,
+        )
+        .await?;
+
+        let persisted_parquet_files: Vec<ParquetFile> = loaded_state
+            .persisted_segments
+            .iter()
+            .flat_map(|segment| segment.databases.values())
+            .flat_map(|db| db.tables.values())
+            .flat_map(|table| table.parquet_files.iter().cloned())
+            .collect();
 
-        let segment_state = Arc::new(RwLock::new(SegmentState::new(
+        let mut segment_state = SegmentState::new(
             segment_duration,
             loaded_state.last_segment_id,
             Arc::clone(&loaded_state.catalog),
@@ -127,7 +216,26 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
             loaded_state.persisting_buffer_segments,
             loaded_state.persisted_segments,
             wal.clone(),
-        )));
+        );
+        let quarantined_parquet_files = persister.load_quarantined_parquet_files().await?;
+        for path in &quarantined_parquet_files {
+            segment_state.quarantine_parquet_file(path);
+        }
+        let segment_state = Arc::new(RwLock::new(segment_state));
+
+        // verify the persisted files in the background so that startup isn't held up reading
+        // all of them
+        let unverified_parquet_files = persisted_parquet_files
+            .into_iter()
+            .filter(|file| {
+                file.checksum.is_some() && !quarantined_parquet_files.contains(&file.path)
+            })
+            .collect();
+        tokio::task::spawn(verify_parquet_files(
+            Arc::clone(&persister),
+            Arc::clone(&segment_state),
+            unverified_parquet_files,
+        ));
 
         let write_buffer_flusher = WriteBufferFlusher::new(Arc::clone(&segment_state));
 
@@ -160,13 +268,38 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
             segment_duration,
             segment_persist_handle: Mutex::new(segment_persist_handle),
             shutdown_segment_persist_tx,
+            persist_backlog: None,
         })
     }
 
+    /// Rejects writes while more than `limit` closed segments are waiting to be persisted,
+    /// reporting the backlog to the given registry.
+    pub fn with_max_persisting_segments(
+        mut self,
+        limit: usize,
+        metrics: &metric::Registry,
+    ) -> Self {
+        self.persist_backlog = Some(PersistBacklog::new(limit, metrics));
+        self
+    }
+
     pub fn catalog(&self) -> Arc<Catalog> {
         Arc::clone(&self.catalog)
     }
 
+    /// Persists the catalog so that changes made to it outside of writes, like database and
+    /// table settings, aren't lost if the server restarts before the next segment is persisted.
+    pub async fn persist_catalog(&self) -> Result<()> {
+        // the persister writes over the newest catalog, whichever segment that was persisted with
+        self.persister
+            .persist_catalog(
+                SegmentId::new(0),
+                Catalog::from_inner(self.catalog.clone_inner()),
+            )
+            .await?;
+        Ok(())
+    }
+
     async fn write_lp(
         &self,
         db_name: NamespaceName<'static>,
@@ -174,32 +307,127 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
         ingest_time: Time,
         accept_partial: bool,
         precision: Precision,
+        source: Option<&str>,
     ) -> Result<BufferedWriteRequest> {
         debug!("write_lp to {} in writebuffer", db_name);
 
-        let result = parse_validate_and_update_catalog(
+        if let Some(persist_backlog) = &self.persist_backlog {
+            let persisting = self.segment_state.read().persisting_segment_count();
+            persist_backlog.check(persisting)?;
+        }
+
+        let db_schema = self.catalog.db_schema(db_name.as_str());
+        let shed_percent = db_schema.as_ref().and_then(|db| db.shed_percent());
+        let quarantine = db_schema.is_some_and(|db| db.quarantine_rejected_writes());
+
+        let result = match parse_validate_and_update_catalog(
             db_name.clone(),
             lp,
             &self.catalog,
             ingest_time,
             self.segment_duration,
             accept_partial,
+            true,
             precision,
-        )?;
+            source,
+            shed_percent,
+        ) {
+            Err(Error::ParseError(e)) if quarantine => {
+                // the write stops at its first rejected line, so the rest of its lines are
+                // validated again, without updating the catalog, to quarantine all of them
+                let db = self
+                    .catalog
+                    .db_schema(db_name.as_str())
+                    .unwrap_or_else(|| Arc::new(DatabaseSchema::new(db_name.as_str())));
+                let errors = parse_validate_and_update_schema(
+                    lp,
+                    &db,
+                    db_name.clone(),
+                    ingest_time,
+                    self.segment_duration,
+                    true,
+                    true,
+                    precision,
+                    source,
+                    shed_percent,
+                    self.catalog.sequence_number(),
+                )
+                .map(|result| result.errors)
+                .ok()
+                .filter(|errors| !errors.is_empty());
+                let errors = errors.as_deref().unwrap_or(std::slice::from_ref(&e));
+                self.quarantine_rejected_lines(&db_name, errors, ingest_time)
+                    .await;
+                return Err(Error::ParseError(e));
+            }
+            result => result?,
+        };
+        if result.shed_lines > 0 {
+            debug!(%db_name, shed_lines = result.shed_lines, "dropped lines to shed load");
+        }
 
         self.write_buffer_flusher
             .write_to_open_segment(result.valid_segmented_data)
             .await?;
 
+        if quarantine && !result.errors.is_empty() {
+            self.quarantine_rejected_lines(&db_name, &result.errors, ingest_time)
+                .await;
+        }
+
         Ok(BufferedWriteRequest {
             db_name,
             invalid_lines: result.errors,
             line_count: result.line_count,
             field_count: result.field_count,
             tag_count: result.tag_count,
+            shed_lines: result.shed_lines,
         })
     }
 
+    /// Writes lines rejected by validation to the rejected writes table of the database. A failure
+    /// to do so is logged rather than returned, so the response to the original write is the
+    /// same whether or not the lines could be quarantined.
+    async fn quarantine_rejected_lines(
+        &self,
+        db_name: &NamespaceName<'static>,
+        errors: &[WriteLineError],
+        ingest_time: Time,
+    ) {
+        let lp = rejected_lines_lp(errors, ingest_time);
+        let result = parse_validate_and_update_catalog(
+            db_name.clone(),
+            &lp,
+            &self.catalog,
+            ingest_time,
+            self.segment_duration,
+            false,
+            true,
+            Precision::Nanosecond,
+            None,
+            None,
+        );
+        let result = match result {
+            Ok(result) => {
+                self.write_buffer_flusher
+                    .write_to_open_segment(result.valid_segmented_data)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!(%db_name, error = %e, "failed to quarantine rejected lines");
+        }
+    }
+
+    /// The time, in nanoseconds, before which data of the database is past its retention period
+    fn retention_cutoff(&self, db_schema: &DatabaseSchema) -> Option<i64> {
+        db_schema
+            .retention_period()
+            .and_then(|retention| self.time_provider.now().checked_sub(retention))
+            .map(|cutoff| cutoff.timestamp_nanos())
+    }
+
     fn get_table_chunks(
         &self,
         database_name: &str,
@@ -224,15 +452,24 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
             table.schema.clone()
         };
 
+        // files outside the time range of the filters, which include the retention filter added
+        // when the table is scanned, are pruned like the buffers are
+        let time_range = filter_time_range(filters);
+        let retained =
+            |parquet_file: &ParquetFile| time_range.overlaps(parquet_file.timestamp_min_max());
+
         let segment_state = self.segment_state.read();
         let mut chunks =
             segment_state.get_table_chunks(db_schema, table_name, filters, projection, ctx)?;
-        let parquet_files = segment_state.get_parquet_files(database_name, table_name);
+        let parquet_files = segment_state
+            .get_parquet_files(database_name, table_name)
+            .into_iter()
+            .filter(|(parquet_file, _)| retained(parquet_file));
 
         let mut chunk_order = chunks.len() as i64;
         let object_store_url = self.persister.object_store_url();
 
-        for parquet_file in parquet_files {
+        for (parquet_file, sort_key) in parquet_files {
             // TODO: update persisted segments to serialize their key to use here
             let partition_key = data_types::PartitionKey::from(parquet_file.path.clone());
             let partition_id = data_types::partition::TransitionPartitionId::new(
@@ -240,12 +477,13 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
                 &partition_key,
             );
 
-            let chunk_stats = create_chunk_statistics(
+            let mut chunk_stats = create_chunk_statistics(
                 Some(parquet_file.row_count as usize),
                 &table_schema,
                 Some(parquet_file.timestamp_min_max()),
-                None,
+                Some(&parquet_file.column_ranges()),
             );
+            add_parquet_null_counts(&mut chunk_stats, &table_schema, &parquet_file);
 
             let location = ObjPath::from(parquet_file.path.clone());
 
@@ -265,7 +503,7 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
                 schema: table_schema.clone(),
                 stats: Arc::new(chunk_stats),
                 partition_id,
-                sort_key: None,
+                sort_key,
                 id: ChunkId::new(),
                 chunk_order: ChunkOrder::new(chunk_order),
                 parquet_exec,
@@ -282,6 +520,8 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
         for parquet_file in self
             .parquet_cache
             .get_parquet_files(database_name, table_name)
+            .into_iter()
+            .filter(|parquet_file| retained(parquet_file))
         {
             let partition_key = data_types::PartitionKey::from(parquet_file.path.clone());
             let partition_id = data_types::partition::TransitionPartitionId::new(
@@ -289,12 +529,13 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
                 &partition_key,
             );
 
-            let chunk_stats = create_chunk_statistics(
+            let mut chunk_stats = create_chunk_statistics(
                 Some(parquet_file.row_count as usize),
                 &table_schema,
                 Some(parquet_file.timestamp_min_max()),
-                None,
+                Some(&parquet_file.column_ranges()),
             );
+            add_parquet_null_counts(&mut chunk_stats, &table_schema, &parquet_file);
 
             let location = ObjPath::from(parquet_file.path.clone());
 
@@ -389,8 +630,9 @@ impl<W: Wal, T: TimeProvider> Bufferer for WriteBufferImpl<W, T> {
         ingest_time: Time,
         accept_partial: bool,
         precision: Precision,
+        source: Option<&str>,
     ) -> Result<BufferedWriteRequest> {
-        self.write_lp(database, lp, ingest_time, accept_partial, precision)
+        self.write_lp(database, lp, ingest_time, accept_partial, precision, source)
             .await
     }
 
@@ -401,6 +643,10 @@ impl<W: Wal, T: TimeProvider> Bufferer for WriteBufferImpl<W, T> {
     fn catalog(&self) -> Arc<Catalog> {
         self.catalog()
     }
+
+    async fn persist_catalog(&self) -> Result<()> {
+        self.persist_catalog().await
+    }
 }
 
 impl<W: Wal, T: TimeProvider> ChunkContainer for WriteBufferImpl<W, T> {
@@ -414,12 +660,107 @@ impl<W: Wal, T: TimeProvider> ChunkContainer for WriteBufferImpl<W, T> {
     ) -> crate::Result<Vec<Arc<dyn QueryChunk>>, DataFusionError> {
         self.get_table_chunks(database_name, table_name, filters, projection, ctx)
     }
+
+    fn retention_filter(&self, database_name: &str) -> Option<Expr> {
+        let db_schema = self.catalog.db_schema(database_name)?;
+        self.retention_cutoff(&db_schema).map(retention_filter)
+    }
+
+    fn parquet_files(&self, database_name: &str, table_name: &str) -> Vec<ParquetFile> {
+        self.segment_state
+            .read()
+            .get_parquet_files(database_name, table_name)
+            .into_iter()
+            .map(|(parquet_file, _)| parquet_file)
+            .collect()
+    }
 }
 
 impl<W: Wal, T: TimeProvider> WriteBuffer for WriteBufferImpl<W, T> {}
 
+/// Checks persisted parquet files against the checksums recorded when they were persisted. Files
+/// that don't match are moved to quarantine and no longer returned as query chunks.
+async fn verify_parquet_files<T: TimeProvider, W: Wal>(
+    persister: Arc<PersisterImpl>,
+    segment_state: Arc<RwLock<SegmentState<T, W>>>,
+    parquet_files: Vec<ParquetFile>,
+) {
+    for parquet_file in parquet_files {
+        match persister.load_verified_parquet_file(&parquet_file).await {
+            Ok(_) => {}
+            Err(persister::Error::ChecksumMismatch { .. }) => {
+                segment_state
+                    .write()
+                    .quarantine_parquet_file(&parquet_file.path);
+            }
+            Err(e) => {
+                warn!(path = %parquet_file.path, error = %e, "failed to verify parquet file");
+            }
+        }
+    }
+}
+
+/// Sets the null count of each column in the statistics that the parquet file recorded
+/// statistics for, so that the planner can use them in its cost estimates.
+fn add_parquet_null_counts(
+    statistics: &mut Statistics,
+    schema: &schema::Schema,
+    parquet_file: &ParquetFile,
+) {
+    let arrow_schema = schema.as_arrow();
+    for (column_stats, field) in statistics
+        .column_statistics
+        .iter_mut()
+        .zip(arrow_schema.fields())
+    {
+        if let Some(file_stats) = parquet_file.column_stats.get(field.name()) {
+            column_stats.null_count = stats::Precision::Exact(file_stats.null_count as usize);
+        }
+    }
+}
+
+/// Keeps the rows at or after the retention cutoff, in nanoseconds
+fn retention_filter(cutoff: i64) -> Expr {
+    col(TIME_COLUMN_NAME).gt_eq(lit_timestamp_nano(cutoff))
+}
+
+/// Whether a line is one of the roughly `percent` of lines dropped to shed load. Lines are chosen
+/// by a hash of their contents, so the same line is always dropped or always kept.
+fn sheds_line(line: &str, percent: NonZeroU8) -> bool {
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish() % 100 < u64::from(percent.get())
+}
+
+/// Builds the line protocol that records rejected lines, and why they were rejected, in the
+/// [`REJECTED_WRITES_TABLE_NAME`] table at the time they were received. The line number is a tag
+/// so that lines rejected from the same write are kept as separate rows.
+fn rejected_lines_lp(errors: &[WriteLineError], ingest_time: Time) -> String {
+    let escape = |s: &str| {
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', " ")
+    };
+
+    errors
+        .iter()
+        .map(|e| {
+            format!(
+                "{REJECTED_WRITES_TABLE_NAME},line_number={} line=\"{}\",error=\"{}\" {}",
+                e.line_number,
+                escape(&e.original_line),
+                escape(&e.error_message),
+                ingest_time.timestamp_nanos(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Returns a validated result and the sequence number of the catalog before any updates were
-/// applied.
+/// applied. Field validation rules are skipped unless `apply_field_rules` is set, which is the
+/// case for everything but WAL replay, as the WAL holds lines the rules were already applied to.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn parse_validate_and_update_catalog(
     db_name: NamespaceName<'static>,
     lp: &str,
@@ -427,7 +768,10 @@ pub(crate) fn parse_validate_and_update_catalog(
     ingest_time: Time,
     segment_duration: SegmentDuration,
     accept_partial: bool,
+    apply_field_rules: bool,
     precision: Precision,
+    source: Option<&str>,
+    shed_percent: Option<NonZeroU8>,
 ) -> Result<ValidationResult> {
     let (sequence, db) = catalog.db_or_create(db_name.as_str())?;
     let mut result = parse_validate_and_update_schema(
@@ -437,7 +781,10 @@ pub(crate) fn parse_validate_and_update_catalog(
         ingest_time,
         segment_duration,
         accept_partial,
+        apply_field_rules,
         precision,
+        source,
+        shed_percent,
         sequence,
     )?;
 
@@ -451,7 +798,9 @@ pub(crate) fn parse_validate_and_update_catalog(
 }
 
 /// Takes &str of line protocol, parses lines, validates the schema, and inserts new columns
-/// if present. Assigns the default time to any lines that do not include a time
+/// if present. Assigns the default time to any lines that do not include a time. If a
+/// `shed_percent` is given, that share of the lines is dropped after parsing, so line numbers
+/// in errors still refer to the lines as written.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn parse_validate_and_update_schema(
     lp: &str,
@@ -460,25 +809,45 @@ pub(crate) fn parse_validate_and_update_schema(
     ingest_time: Time,
     segment_duration: SegmentDuration,
     accept_partial: bool,
+    apply_field_rules: bool,
     precision: Precision,
+    source: Option<&str>,
+    shed_percent: Option<NonZeroU8>,
     starting_catalog_sequence_number: SequenceNumber,
 ) -> Result<ValidationResult> {
     let mut errors = vec![];
-    let mut lp_lines = lp.lines();
+    let mut shed_lines = 0;
 
     let mut valid_parsed_and_raw_lines: Vec<(ParsedLine, &str)> = vec![];
 
-    for (line_idx, maybe_line) in parse_lines(lp).enumerate() {
+    // each line is parsed on its own to keep it alongside its raw text, skipping the blank and
+    // comment lines that parse_lines skips
+    let lines = split_lines(lp)
+        .filter_map(|raw_line| parse_lines(raw_line).next().map(|line| (line, raw_line)));
+
+    for (line_idx, (maybe_line, raw_line)) in lines.enumerate() {
+        if shed_percent.is_some_and(|percent| sheds_line(raw_line, percent)) {
+            shed_lines += 1;
+            continue;
+        }
+
         let line = match maybe_line
             .map_err(|e| WriteLineError {
-                // This unwrap is fine because we're moving line by line
-                // alongside the output from parse_lines
-                original_line: lp_lines.next().unwrap().to_string(),
+                original_line: raw_line.to_string(),
                 line_number: line_idx + 1,
                 error_message: e.to_string(),
             })
-            .and_then(|l| validate_line_schema(line_idx, l, schema))
-        {
+            .and_then(|l| {
+                validate_line_schema(
+                    line_idx,
+                    l,
+                    raw_line,
+                    schema,
+                    ingest_time,
+                    precision,
+                    apply_field_rules,
+                )
+            }) {
             Ok(line) => line,
             Err(e) => {
                 if !accept_partial {
@@ -489,9 +858,7 @@ pub(crate) fn parse_validate_and_update_schema(
                 continue;
             }
         };
-        // This unwrap is fine because we're moving line by line
-        // alongside the output from parse_lines
-        valid_parsed_and_raw_lines.push((line, lp_lines.next().unwrap()));
+        valid_parsed_and_raw_lines.push((line, raw_line));
     }
 
     validate_or_insert_schema_and_partitions(
@@ -500,11 +867,14 @@ pub(crate) fn parse_validate_and_update_schema(
         db_name,
         ingest_time,
         segment_duration,
+        apply_field_rules,
         precision,
+        source,
         starting_catalog_sequence_number,
     )
     .map(move |mut result| {
         result.errors = errors;
+        result.shed_lines = shed_lines;
         result
     })
 }
@@ -514,11 +884,60 @@ pub(crate) fn parse_validate_and_update_schema(
 /// This is for scenarios where a write comes in for a table that exists, but may have invalid field
 /// types, based on the pre-existing schema.
 fn validate_line_schema<'a>(
-    line_number: usize,
+    line_idx: usize,
     line: ParsedLine<'a>,
+    raw_line: &str,
     schema: &DatabaseSchema,
+    ingest_time: Time,
+    precision: Precision,
+    apply_field_rules: bool,
 ) -> Result<ParsedLine<'a>, WriteLineError> {
+    let line_number = line_idx + 1;
     let table_name = line.series.measurement.as_str();
+    // the lines of the rejected writes table record why other lines were rejected, so they're
+    // exempt from validation
+    let rejected_writes = table_name == REJECTED_WRITES_TABLE_NAME;
+
+    let time_nanos = match line.timestamp {
+        Some(ts) => timestamp_nanos(ts, precision).ok_or_else(|| WriteLineError {
+            original_line: raw_line.to_string(),
+            line_number,
+            error_message: format!(
+                "invalid timestamp in line protocol on line {line_number}: {ts} is out of range \
+                for precision {precision:?}",
+                precision = timestamp_precision(ts, precision),
+            ),
+        })?,
+        None => ingest_time.timestamp_nanos(),
+    };
+
+    if let Some(time_validation) = schema.time_validation().filter(|_| !rejected_writes) {
+        let line_precision = line.timestamp.map(|ts| timestamp_precision(ts, precision));
+        if let Err(reason) =
+            time_validation.check(time_nanos, line_precision, ingest_time.timestamp_nanos())
+        {
+            return Err(WriteLineError {
+                original_line: raw_line.to_string(),
+                line_number,
+                error_message: format!(
+                    "invalid timestamp in line protocol on line {line_number}: {reason}"
+                ),
+            });
+        }
+    }
+
+    if schema.strict_tables() && !rejected_writes && !schema.table_exists(table_name) {
+        return Err(WriteLineError {
+            original_line: raw_line.to_string(),
+            line_number,
+            error_message: format!(
+                "table '{table_name}' on line {line_number} does not exist and database \
+                '{db_name}' requires tables to be created before they are written to",
+                db_name = schema.name,
+            ),
+        });
+    }
+
     if let Some(table_schema) = schema.get_table_schema(table_name) {
         for (field_name, field_val) in line.field_set.iter() {
             if let Some(schema_col_type) = table_schema.field_type_by_name(field_name) {
@@ -526,8 +945,8 @@ fn validate_line_schema<'a>(
                 if field_col_type != schema_col_type {
                     let field_name = field_name.to_string();
                     return Err(WriteLineError {
-                        original_line: line.to_string(),
-                        line_number: line_number + 1,
+                        original_line: raw_line.to_string(),
+                        line_number,
                         error_message: format!(
                             "invalid field value in line protocol for field '{field_name}' on line \
                             {line_number}: expected type {expected}, but got {got}",
@@ -540,6 +959,30 @@ fn validate_line_schema<'a>(
         }
     }
 
+    if let Some(table) = schema
+        .get_table(table_name)
+        .filter(|_| apply_field_rules && !rejected_writes)
+    {
+        for (field_name, field_val) in line.field_set.iter() {
+            if let Some(validation) = table.field_validation(field_name.as_str()) {
+                if validation.action == ValidationAction::Reject
+                    && !validation.rule.allows(field_val)
+                {
+                    let field_name = field_name.to_string();
+                    return Err(WriteLineError {
+                        original_line: raw_line.to_string(),
+                        line_number,
+                        error_message: format!(
+                            "invalid field value in line protocol for field '{field_name}' on line \
+                            {line_number}: value {field_val} does not satisfy rule {rule:?}",
+                            rule = validation.rule,
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
     Ok(line)
 }
 
@@ -547,13 +990,16 @@ fn validate_line_schema<'a>(
 /// are passed back as a new DatabaseSchema as part of the ValidationResult. Lines are split
 /// into partitions and the validation result contains the data that can then be serialized
 /// into the WAL.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn validate_or_insert_schema_and_partitions(
     lines: Vec<(ParsedLine<'_>, &str)>,
     schema: &DatabaseSchema,
     db_name: NamespaceName<'static>,
     ingest_time: Time,
     segment_duration: SegmentDuration,
+    apply_field_rules: bool,
     precision: Precision,
+    source: Option<&str>,
     starting_catalog_sequence_number: SequenceNumber,
 ) -> Result<ValidationResult> {
     // The (potentially updated) DatabaseSchema to return to the caller.
@@ -577,7 +1023,9 @@ pub(crate) fn validate_or_insert_schema_and_partitions(
             &mut schema,
             ingest_time,
             segment_duration,
+            apply_field_rules,
             precision,
+            source,
         )?;
     }
 
@@ -597,6 +1045,7 @@ pub(crate) fn validate_or_insert_schema_and_partitions(
                 lp: table_batches.lines.join("\n"),
                 default_time: ingest_time.timestamp_nanos(),
                 precision,
+                source: source.map(ToString::to_string),
             }),
             starting_catalog_sequence_number,
         })
@@ -608,6 +1057,7 @@ pub(crate) fn validate_or_insert_schema_and_partitions(
         field_count,
         tag_count,
         errors: vec![],
+        shed_lines: 0,
         valid_segmented_data,
     })
 }
@@ -615,7 +1065,12 @@ pub(crate) fn validate_or_insert_schema_and_partitions(
 /// Check if the table exists in the schema and update the schema if it does not
 // Because the entry API requires &mut it is not used to avoid a premature
 // clone of the Cow.
-fn validate_and_update_schema(line: &ParsedLine<'_>, schema: &mut Cow<'_, DatabaseSchema>) {
+fn validate_and_update_schema(
+    line: &ParsedLine<'_>,
+    schema: &mut Cow<'_, DatabaseSchema>,
+    ingest_time: Time,
+) {
+    let time = ingest_time.timestamp_nanos();
     let table_name = line.series.measurement.as_str();
     match schema.tables.get(table_name) {
         Some(t) => {
@@ -636,7 +1091,7 @@ fn validate_and_update_schema(line: &ParsedLine<'_>, schema: &mut Cow<'_, Databa
 
             if !new_cols.is_empty() {
                 let t = schema.to_mut().tables.get_mut(table_name).unwrap();
-                t.add_columns(new_cols);
+                t.add_columns(new_cols, time, SchemaChangeOrigin::Write);
             }
         }
         None => {
@@ -652,7 +1107,8 @@ fn validate_and_update_schema(line: &ParsedLine<'_>, schema: &mut Cow<'_, Databa
 
             columns.insert(TIME_COLUMN_NAME.to_string(), ColumnType::Time as i16);
 
-            let table = TableDefinition::new(table_name, columns);
+            let table =
+                TableDefinition::new_logged(table_name, columns, time, SchemaChangeOrigin::Write);
 
             assert!(schema
                 .to_mut()
@@ -663,27 +1119,101 @@ fn validate_and_update_schema(line: &ParsedLine<'_>, schema: &mut Cow<'_, Databa
     };
 }
 
+/// The precision of a line's timestamp, guessed from its value if the write didn't specify one
+fn timestamp_precision(ts: i64, precision: Precision) -> Precision {
+    match precision {
+        Precision::Auto => crate::guess_precision(ts),
+        precision => precision,
+    }
+}
+
+/// Converts a line's timestamp to nanoseconds, returning `None` if it doesn't fit
+fn timestamp_nanos(ts: i64, precision: Precision) -> Option<i64> {
+    let multiplier = match timestamp_precision(ts, precision) {
+        Precision::Second => 1_000_000_000,
+        Precision::Millisecond => 1_000_000,
+        Precision::Microsecond => 1_000,
+        Precision::Nanosecond => 1,
+
+        Precision::Auto => unreachable!(),
+    };
+
+    ts.checked_mul(multiplier)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn validate_and_convert_parsed_line<'a>(
-    line: ParsedLine<'_>,
+    mut line: ParsedLine<'_>,
     raw_line: &'a str,
     segment_table_batches: &mut HashMap<Time, TableBatchMap<'a>>,
     schema: &mut Cow<'_, DatabaseSchema>,
     ingest_time: Time,
     segment_duration: SegmentDuration,
+    apply_field_rules: bool,
     precision: Precision,
+    source: Option<&str>,
 ) -> Result<()> {
-    validate_and_update_schema(&line, schema);
+    validate_and_update_schema(&line, schema, ingest_time);
 
     // now that we've ensured all columns exist in the schema, construct the actual row and values
     // while validating the column types match.
     let mut values = Vec::with_capacity(line.column_count() + 1);
 
+    let table = schema.get_table(line.series.measurement.as_str());
+    let rejected_writes = line.series.measurement.as_str() == REJECTED_WRITES_TABLE_NAME;
+
+    // apply the field validation rules, giving the WAL the line with the values they produced,
+    // as replay doesn't apply them again. Rejected writes are exempt, as in validate_line_schema
+    let mut wal_line = Cow::Borrowed(raw_line);
+    let mut suspect = false;
+    if let Some(table) = table.filter(|_| apply_field_rules && !rejected_writes) {
+        let mut clamped = false;
+        for (field_name, value) in line.field_set.iter_mut() {
+            match table.field_validation(field_name.as_str()) {
+                Some(validation) if !validation.rule.allows(value) => match validation.action {
+                    ValidationAction::Clamp => {
+                        *value = validation.rule.clamp(value.clone());
+                        clamped = true;
+                    }
+                    ValidationAction::TagAsSuspect => suspect = true,
+                    // lines with rejected values have already been filtered out
+                    ValidationAction::Reject => {}
+                },
+                _ => {}
+            }
+        }
+
+        let tagged = line.series.tag_set.as_ref().is_some_and(|tags| {
+            tags.iter()
+                .any(|(tag_key, _)| tag_key.as_str() == SUSPECT_TAG_NAME)
+        });
+        if clamped || (suspect && !tagged) {
+            let series = line.series.to_string();
+            let line = line.to_string();
+            let suspect_tag = if suspect && !tagged {
+                format!(",{SUSPECT_TAG_NAME}=true")
+            } else {
+                String::new()
+            };
+            wal_line = Cow::Owned(format!("{series}{suspect_tag}{}", &line[series.len()..]));
+        }
+    }
+
     // validate tags, collecting any new ones that must be inserted, or adding the values
     if let Some(tag_set) = line.series.tag_set {
+        let normalization = table.and_then(|t| t.tag_normalization());
         for (tag_key, value) in tag_set {
+            let value = match normalization {
+                Some(normalization) => match normalization.normalize(value.as_str()) {
+                    Some(value) => value,
+                    // the tag is left out of the row, as if it hadn't been written
+                    None => continue,
+                },
+                None => value.to_string(),
+            };
             let value = Field {
                 name: tag_key.to_string(),
-                value: FieldData::Tag(value.to_string()),
+                value: FieldData::Tag(value),
             };
             values.push(value);
         }
@@ -705,26 +1235,44 @@ fn validate_and_convert_parsed_line<'a>(
         values.push(value);
     }
 
+    if suspect && !values.iter().any(|f| f.name == SUSPECT_TAG_NAME) {
+        let table_name = line.series.measurement.as_str();
+        if !schema
+            .get_table(table_name)
+            .is_some_and(|t| t.column_exists(SUSPECT_TAG_NAME))
+        {
+            schema
+                .to_mut()
+                .tables
+                .get_mut(table_name)
+                .expect("table was added to the schema above")
+                .add_columns(
+                    vec![(SUSPECT_TAG_NAME.to_string(), ColumnType::Tag as i16)],
+                    ingest_time.timestamp_nanos(),
+                    SchemaChangeOrigin::Server,
+                );
+        }
+        values.push(Field {
+            name: SUSPECT_TAG_NAME.to_string(),
+            value: FieldData::Tag("true".to_string()),
+        });
+    }
+
+    if schema.provenance_columns {
+        add_provenance_values(
+            line.series.measurement.as_str(),
+            &mut values,
+            schema,
+            ingest_time,
+            source,
+        );
+    }
+
     // set the time value
     let time_value_nanos = line
         .timestamp
         .map(|ts| {
-            let multiplier = match precision {
-                Precision::Auto => match crate::guess_precision(ts) {
-                    Precision::Second => 1_000_000_000,
-                    Precision::Millisecond => 1_000_000,
-                    Precision::Microsecond => 1_000,
-                    Precision::Nanosecond => 1,
-
-                    Precision::Auto => unreachable!(),
-                },
-                Precision::Second => 1_000_000_000,
-                Precision::Millisecond => 1_000_000,
-                Precision::Microsecond => 1_000,
-                Precision::Nanosecond => 1,
-            };
-
-            ts * multiplier
+            timestamp_nanos(ts, precision).expect("timestamps are checked when lines are validated")
         })
         .unwrap_or(ingest_time.timestamp_nanos());
 
@@ -746,11 +1294,59 @@ fn validate_and_convert_parsed_line<'a>(
         fields: values,
     });
 
-    table_batch_map.lines.push(raw_line);
+    table_batch_map.lines.push(wal_line);
 
     Ok(())
 }
 
+/// Adds the `_ingested_at` and `_source` provenance values to a row, inserting the columns into
+/// the table schema if they aren't already there. Values the line already set are kept.
+fn add_provenance_values(
+    table_name: &str,
+    values: &mut Vec<Field>,
+    schema: &mut Cow<'_, DatabaseSchema>,
+    ingest_time: Time,
+    source: Option<&str>,
+) {
+    let mut provenance = vec![(
+        INGESTED_AT_COLUMN_NAME,
+        ColumnType::I64,
+        FieldData::Integer(ingest_time.timestamp_nanos()),
+    )];
+    if let Some(source) = source {
+        provenance.push((
+            SOURCE_COLUMN_NAME,
+            ColumnType::Tag,
+            FieldData::Tag(source.to_string()),
+        ));
+    }
+
+    for (name, column_type, value) in provenance {
+        if values.iter().any(|f| f.name == name) {
+            continue;
+        }
+        if !schema
+            .get_table(table_name)
+            .is_some_and(|t| t.column_exists(name))
+        {
+            schema
+                .to_mut()
+                .tables
+                .get_mut(table_name)
+                .expect("table was added to the schema above")
+                .add_columns(
+                    vec![(name.to_string(), column_type as i16)],
+                    ingest_time.timestamp_nanos(),
+                    SchemaChangeOrigin::Server,
+                );
+        }
+        values.push(Field {
+            name: name.to_string(),
+            value,
+        });
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct TableBatch {
     #[allow(dead_code)]
@@ -814,6 +1410,8 @@ pub(crate) struct ValidationResult {
     pub(crate) tag_count: usize,
     /// Any errors that occurred while parsing the lines
     pub(crate) errors: Vec<crate::WriteLineError>,
+    /// Number of lines dropped to shed load
+    pub(crate) shed_lines: usize,
     /// Only valid lines from what was passed in to validate, segmented based on the
     /// timestamps of the data.
     pub(crate) valid_segmented_data: Vec<ValidSegmentedData>,
@@ -831,7 +1429,7 @@ pub(crate) struct ValidSegmentedData {
 
 #[derive(Debug, Default)]
 pub(crate) struct TableBatchMap<'a> {
-    pub(crate) lines: Vec<&'a str>,
+    pub(crate) lines: Vec<Cow<'a, str>>,
     pub(crate) table_batches: HashMap<String, TableBatch>,
 }
 
@@ -860,16 +1458,25 @@ impl Default for SeriesId {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::catalog::{FieldRule, FieldValidation, TagNormalization, TimeValidation};
+    use crate::paths::{ParquetFilePath, QuarantineFilePath};
     use crate::persister::PersisterImpl;
     use crate::wal::WalImpl;
-    use crate::{SegmentId, SequenceNumber, WalOpBatch};
+    use crate::{DatabaseTables, PersistedSegment, SequenceNumber, TableParquetFiles, WalOpBatch};
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
     use arrow::record_batch::RecordBatch;
     use arrow_util::assert_batches_eq;
+    use bytes::Bytes;
+    use chrono::Utc;
+    use datafusion::physical_plan::stream::RecordBatchReceiverStreamBuilder;
     use datafusion_util::config::register_iox_object_store;
     use iox_query::exec::IOxSessionContext;
     use iox_time::{MockProvider, Time};
     use object_store::memory::InMemory;
     use object_store::ObjectStore;
+    use std::num::NonZeroU64;
+    use std::time::Duration;
 
     #[test]
     fn parse_lp_into_buffer() {
@@ -883,7 +1490,10 @@ mod tests {
             Time::from_timestamp_nanos(0),
             SegmentDuration::new_5m(),
             false,
+            true,
             Precision::Nanosecond,
+            None,
+            None,
             SequenceNumber::new(0),
         )
         .unwrap();
@@ -895,6 +1505,467 @@ mod tests {
         assert_eq!(db.tables.get("foo").unwrap().columns().len(), 2);
     }
 
+    #[test]
+    fn logs_schema_changes() {
+        let catalog = Catalog::new();
+        let db_name = NamespaceName::new("foo").unwrap();
+        for (lp, time) in [
+            ("cpu,host=a usage=1 1", 10),
+            ("cpu,host=a,region=us usage=2 2", 20),
+        ] {
+            parse_validate_and_update_catalog(
+                db_name.clone(),
+                lp,
+                &catalog,
+                Time::from_timestamp_nanos(time),
+                SegmentDuration::new_5m(),
+                false,
+                true,
+                Precision::Nanosecond,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        let db = catalog.db_schema("foo").unwrap();
+        let changes = db
+            .get_table("cpu")
+            .unwrap()
+            .schema_changes()
+            .iter()
+            .map(|c| (c.column.as_str(), c.time, c.origin))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                ("host", 10, SchemaChangeOrigin::Write),
+                ("time", 10, SchemaChangeOrigin::Write),
+                ("usage", 10, SchemaChangeOrigin::Write),
+                ("region", 20, SchemaChangeOrigin::Write),
+            ]
+        );
+    }
+
+    #[test]
+    fn strict_tables_reject_unknown_tables() {
+        let catalog = Catalog::new();
+        let db_name = NamespaceName::new("foo").unwrap();
+        catalog
+            .create_table("foo", "cpu", &["host".to_string()], 0)
+            .unwrap();
+        catalog.set_strict_tables("foo", true).unwrap();
+
+        let result = parse_validate_and_update_catalog(
+            db_name,
+            "cpu,host=a usage=1 1\ncpy,host=a usage=2 2",
+            &catalog,
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            true,
+            true,
+            Precision::Nanosecond,
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(result.line_count, 1);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].line_number, 2);
+        assert!(result.errors[0].error_message.contains("'cpy'"));
+
+        let db = catalog.db_schema("foo").unwrap();
+        assert_eq!(db.table_names(), vec!["cpu".to_string()]);
+        assert!(db.get_table("cpu").unwrap().column_exists("usage"));
+    }
+
+    #[test]
+    fn sheds_lines() {
+        // every tenth line fails to parse
+        let lines = (0..1000)
+            .map(|i| {
+                if i % 10 == 0 {
+                    format!("cpu,host=a,bad {i}")
+                } else {
+                    format!("cpu,host=a usage={i} {i}")
+                }
+            })
+            .collect::<Vec<_>>();
+        let lp = lines.join("\n");
+        let validate = |percent| {
+            parse_validate_and_update_catalog(
+                NamespaceName::new("foo").unwrap(),
+                &lp,
+                &Catalog::new(),
+                Time::from_timestamp_nanos(0),
+                SegmentDuration::new_5m(),
+                true,
+                true,
+                Precision::Nanosecond,
+                None,
+                NonZeroU8::new(percent),
+            )
+            .unwrap()
+        };
+
+        let result = validate(30);
+        let shed = result.shed_lines;
+        assert!((200..400).contains(&shed), "shed {shed} lines");
+        assert_eq!(result.line_count + result.errors.len(), 1000 - shed);
+        // the lines that are kept have the line numbers they were written with
+        assert!(!result.errors.is_empty());
+        for error in &result.errors {
+            assert_eq!(error.original_line, lines[error.line_number - 1]);
+        }
+        // the same lines are dropped every time
+        assert_eq!(validate(30).shed_lines, shed);
+
+        let result = validate(100);
+        assert_eq!(result.shed_lines, 1000);
+        assert_eq!(result.line_count, 0);
+        assert!(result.errors.is_empty());
+
+        let catalog = Catalog::new();
+        assert!(catalog
+            .set_load_shedding("foo", NonZeroU8::new(101))
+            .is_err());
+    }
+
+    #[test]
+    fn applies_field_validation_rules() {
+        let catalog = Catalog::new();
+        let db_name = NamespaceName::new("foo").unwrap();
+        parse_validate_and_update_catalog(
+            db_name.clone(),
+            "cpu,host=a usage=1,state=\"ok\",temp=1 1",
+            &catalog,
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            false,
+            true,
+            Precision::Nanosecond,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let range = |min, max| FieldRule::Range {
+            min: Some(min),
+            max: Some(max),
+        };
+        catalog
+            .set_field_validation(
+                "foo",
+                "cpu",
+                "usage",
+                Some(FieldValidation {
+                    rule: range(0.0, 100.0),
+                    action: ValidationAction::Clamp,
+                }),
+            )
+            .unwrap();
+        catalog
+            .set_field_validation(
+                "foo",
+                "cpu",
+                "state",
+                Some(FieldValidation {
+                    rule: FieldRule::Enum {
+                        values: ["ok".to_string(), "warn".to_string()].into(),
+                    },
+                    action: ValidationAction::TagAsSuspect,
+                }),
+            )
+            .unwrap();
+        catalog
+            .set_field_validation(
+                "foo",
+                "cpu",
+                "temp",
+                Some(FieldValidation {
+                    rule: range(-50.0, 200.0),
+                    action: ValidationAction::Reject,
+                }),
+            )
+            .unwrap();
+
+        let lp = "cpu,host=a usage=150,state=\"ok\",temp=20 2\n\
+                  cpu,host=a usage=10,state=\"bad\",temp=20 3\n\
+                  cpu,host=a usage=10,state=\"ok\",temp=500 4";
+        let result = parse_validate_and_update_catalog(
+            db_name,
+            lp,
+            &catalog,
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            true,
+            true,
+            Precision::Nanosecond,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // the out of range temp is rejected
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].line_number, 3);
+
+        let rows = &result.valid_segmented_data[0].table_batches["cpu"].rows;
+        assert_eq!(rows.len(), 2);
+        let suspect = Field {
+            name: SUSPECT_TAG_NAME.to_string(),
+            value: FieldData::Tag("true".to_string()),
+        };
+
+        // the out of range usage is clamped
+        assert!(rows[0].fields.contains(&Field {
+            name: "usage".to_string(),
+            value: FieldData::Float(100.0),
+        }));
+        assert!(!rows[0].fields.contains(&suspect));
+
+        // the unknown state is accepted but tagged
+        assert!(rows[1].fields.contains(&suspect));
+        assert!(catalog
+            .db_schema("foo")
+            .unwrap()
+            .get_table("cpu")
+            .unwrap()
+            .column_exists(SUSPECT_TAG_NAME));
+
+        // the wal is given the lines with the values the rules produced, as they aren't applied
+        // again on replay
+        let WalOp::LpWrite(write) = &result.valid_segmented_data[0].wal_op;
+        let lines = write.lp.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("usage=100,"));
+        assert!(lines[1].starts_with("cpu,host=a,_suspect=true "));
+    }
+
+    #[test]
+    fn adds_provenance_columns_when_enabled() {
+        let catalog = Catalog::new();
+        catalog.set_provenance_columns("foo", true).unwrap();
+
+        let mut result = parse_validate_and_update_catalog(
+            NamespaceName::new("foo").unwrap(),
+            "cpu,host=a usage=1 10",
+            &catalog,
+            Time::from_timestamp_nanos(123),
+            SegmentDuration::new_5m(),
+            false,
+            true,
+            Precision::Nanosecond,
+            Some("token:abc"),
+            None,
+        )
+        .unwrap();
+
+        let segment_data = result.valid_segmented_data.pop().unwrap();
+        let row = &segment_data.table_batches["cpu"].rows[0];
+        assert!(row.fields.contains(&Field {
+            name: INGESTED_AT_COLUMN_NAME.to_string(),
+            value: FieldData::Integer(123),
+        }));
+        assert!(row.fields.contains(&Field {
+            name: SOURCE_COLUMN_NAME.to_string(),
+            value: FieldData::Tag("token:abc".to_string()),
+        }));
+
+        // the source is kept in the wal so that replay adds the same values
+        let WalOp::LpWrite(op) = segment_data.wal_op;
+        assert_eq!(op.source.as_deref(), Some("token:abc"));
+
+        let db = catalog.db_schema("foo").unwrap();
+        let table = db.get_table("cpu").unwrap();
+        assert!(table.column_exists(INGESTED_AT_COLUMN_NAME));
+        assert!(table.column_exists(SOURCE_COLUMN_NAME));
+    }
+
+    #[test]
+    fn normalizes_tag_values() {
+        let catalog = Catalog::new();
+        let db_name = NamespaceName::new("foo").unwrap();
+        parse_validate_and_update_catalog(
+            db_name.clone(),
+            "cpu,host=a usage=1 1",
+            &catalog,
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            false,
+            true,
+            Precision::Nanosecond,
+            None,
+            None,
+        )
+        .unwrap();
+        catalog
+            .set_tag_normalization(
+                "foo",
+                "cpu",
+                Some(TagNormalization {
+                    lowercase: true,
+                    trim: true,
+                    mappings: [("web-1".to_string(), "web01".to_string())].into(),
+                }),
+            )
+            .unwrap();
+
+        let lp = "cpu,host=HOST usage=1 2\n\
+                  cpu,host=\\ Host\\  usage=2 3\n\
+                  cpu,host=Web-1 usage=3 4\n\
+                  cpu,host=\\ \\  usage=4 5";
+        let result = parse_validate_and_update_catalog(
+            db_name,
+            lp,
+            &catalog,
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            false,
+            true,
+            Precision::Nanosecond,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let hosts: Vec<_> = result.valid_segmented_data[0].table_batches["cpu"]
+            .rows
+            .iter()
+            .map(|row| {
+                row.fields
+                    .iter()
+                    .find(|f| f.name == "host")
+                    .map(|f| f.value.clone())
+            })
+            .collect();
+        // a value that is empty once trimmed drops the tag from the row
+        assert_eq!(
+            hosts,
+            vec![
+                Some(FieldData::Tag("host".to_string())),
+                Some(FieldData::Tag("host".to_string())),
+                Some(FieldData::Tag("web01".to_string())),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_points_failing_time_validation() {
+        let catalog = Catalog::new();
+        catalog
+            .set_time_validation(
+                "foo",
+                Some(TimeValidation {
+                    precision: Some(Precision::Second),
+                    max_future_seconds: Some(3600),
+                    min_time: Some(946_684_800_000_000_000),
+                }),
+            )
+            .unwrap();
+
+        let now = Time::from_timestamp(1_700_000_000, 0).unwrap();
+        let lp = "cpu,host=a usage=1 1700000000\n\
+                  cpu,host=a usage=2 4300000000\n\
+                  cpu,host=a usage=3 900000000\n\
+                  cpu,host=a usage=4 1700000000000\n\
+                  cpu,host=a usage=5";
+        let result = parse_validate_and_update_catalog(
+            NamespaceName::new("foo").unwrap(),
+            lp,
+            &catalog,
+            now,
+            SegmentDuration::new_5m(),
+            true,
+            true,
+            Precision::Auto,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // too far in the future, before the minimum time, and written in milliseconds
+        let rejected: Vec<_> = result.errors.iter().map(|e| e.line_number).collect();
+        assert_eq!(rejected, vec![2, 3, 4]);
+        assert!(result.errors[0].error_message.contains("on line 2:"));
+        assert!(result.errors[0].error_message.contains("in the future"));
+
+        let rows: usize = result
+            .valid_segmented_data
+            .iter()
+            .map(|data| data.table_batches["cpu"].rows.len())
+            .sum();
+        assert_eq!(rows, 2);
+    }
+
+    #[test]
+    fn rejects_timestamps_overflowing_nanoseconds() {
+        let catalog = Catalog::new();
+        let lp = "cpu,host=a usage=1 1700000000\n\
+                  cpu,host=a usage=2 9223372036854775";
+        let result = parse_validate_and_update_catalog(
+            NamespaceName::new("foo").unwrap(),
+            lp,
+            &catalog,
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            true,
+            true,
+            Precision::Second,
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].line_number, 2);
+        assert!(result.errors[0]
+            .error_message
+            .contains("on line 2: 9223372036854775 is out of range"));
+        assert_eq!(
+            result.valid_segmented_data[0].table_batches["cpu"]
+                .rows
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn persist_backlog_rejects_writes_until_caught_up() {
+        let metrics = metric::Registry::new();
+        let backlog = PersistBacklog::new(2, &metrics);
+        let gauge = |name| {
+            metrics
+                .get_instrument::<metric::Metric<U64Gauge>>(name)
+                .unwrap()
+                .get_observer(&metric::Attributes::from(&[]))
+                .unwrap()
+                .fetch()
+        };
+        assert!(backlog.check(2).is_ok());
+        assert_eq!(gauge("influxdb3_persist_backlog_segments"), 2);
+        assert_eq!(gauge("influxdb3_persist_backlog_catching_up"), 0);
+
+        let err = backlog.check(3).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PersistBacklog {
+                persisting: 3,
+                limit: 2
+            }
+        ));
+        assert!(backlog.catching_up.load(Ordering::Relaxed));
+        assert_eq!(gauge("influxdb3_persist_backlog_segments"), 3);
+        assert_eq!(gauge("influxdb3_persist_backlog_catching_up"), 1);
+
+        assert!(backlog.check(1).is_ok());
+        assert!(!backlog.catching_up.load(Ordering::Relaxed));
+        assert_eq!(gauge("influxdb3_persist_backlog_segments"), 1);
+        assert_eq!(gauge("influxdb3_persist_backlog_catching_up"), 0);
+    }
+
     #[tokio::test]
     async fn buffers_and_persists_to_wal() {
         let dir = test_helpers::tmp_dir().unwrap().into_path();
@@ -909,6 +1980,7 @@ mod tests {
             Arc::clone(&time_provider),
             segment_duration,
             crate::test_help::make_exec(),
+            None,
         )
         .await
         .unwrap();
@@ -920,6 +1992,7 @@ mod tests {
                 Time::from_timestamp_nanos(123),
                 false,
                 Precision::Nanosecond,
+                None,
             )
             .await
             .unwrap();
@@ -949,6 +2022,7 @@ mod tests {
                 lp: "cpu bar=1 10".to_string(),
                 default_time: 123,
                 precision: Precision::Nanosecond,
+                source: None,
             })],
         };
         assert_eq!(batch, expected_batch);
@@ -960,13 +2034,294 @@ mod tests {
             time_provider,
             segment_duration,
             crate::test_help::make_exec(),
+            None,
+        )
+        .await
+        .unwrap();
+        let actual = write_buffer.get_table_record_batches("foo", "cpu");
+        assert_batches_eq!(&expected, &actual);
+    }
+
+    #[tokio::test]
+    async fn catalog_settings_survive_restart() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let write_buffer = WriteBufferImpl::new(
+            Arc::new(PersisterImpl::new(Arc::clone(&object_store))),
+            None::<Arc<WalImpl>>,
+            Arc::clone(&time_provider),
+            SegmentDuration::new_5m(),
+            crate::test_help::make_exec(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let catalog = write_buffer.catalog();
+        catalog.set_strict_tables("foo", true).unwrap();
+        catalog
+            .create_table("foo", "cpu", &["host".to_string()], 0)
+            .unwrap();
+        catalog
+            .set_retention_period("foo", NonZeroU64::new(3_600_000))
+            .unwrap();
+        write_buffer.persist_catalog().await.unwrap();
+
+        // no segment has been persisted, but the settings are loaded after a restart
+        let write_buffer = WriteBufferImpl::new(
+            Arc::new(PersisterImpl::new(Arc::clone(&object_store))),
+            None::<Arc<WalImpl>>,
+            time_provider,
+            SegmentDuration::new_5m(),
+            crate::test_help::make_exec(),
+            None,
         )
         .await
         .unwrap();
+        let db = write_buffer.catalog().db_schema("foo").unwrap();
+        assert!(db.strict_tables());
+        assert!(db.table_exists("cpu"));
+        assert_eq!(db.retention_period(), Some(Duration::from_secs(3600)));
+    }
+
+    #[tokio::test]
+    async fn quarantines_persisted_parquet_files_failing_verification() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let mut parquet_files = vec![];
+        for file_number in 1..=3 {
+            let stream_builder = RecordBatchReceiverStreamBuilder::new(Arc::clone(&schema), 1);
+            let batch = RecordBatch::try_new(
+                Arc::clone(&schema),
+                vec![Arc::new(Int64Array::from(vec![file_number]))],
+            )
+            .unwrap();
+            stream_builder.tx().send(Ok(batch)).await.unwrap();
+            let path = ParquetFilePath::new("foo", "cpu", Utc::now(), file_number as u32);
+            let (size_bytes, meta, checksum) = persister
+                .persist_parquet_file(path.clone(), stream_builder.build())
+                .await
+                .unwrap();
+            parquet_files.push(ParquetFile {
+                path: path.to_string(),
+                size_bytes,
+                row_count: meta.num_rows as u64,
+                min_time: 0,
+                max_time: 0,
+                checksum: Some(checksum),
+                column_stats: Default::default(),
+                column_sizes: Default::default(),
+            });
+        }
+        persister
+            .persist_segment(&PersistedSegment {
+                segment_id: SegmentId::new(1),
+                segment_wal_size_bytes: 0,
+                segment_parquet_size_bytes: 0,
+                segment_row_count: 3,
+                segment_min_time: 0,
+                segment_max_time: 0,
+                databases: HashMap::from([(
+                    "foo".to_string(),
+                    DatabaseTables {
+                        tables: HashMap::from([(
+                            "cpu".to_string(),
+                            TableParquetFiles {
+                                table_name: "cpu".to_string(),
+                                parquet_files: parquet_files.clone(),
+                                sort_key: vec![],
+                            },
+                        )]),
+                    },
+                )]),
+                late_data: false,
+                fencing_epoch: 0,
+            })
+            .await
+            .unwrap();
+
+        // the second file is corrupted and the third was quarantined before a restart
+        object_store
+            .put(
+                &ObjPath::from(parquet_files[1].path.as_str()),
+                Bytes::from_static(b"not a parquet file"),
+            )
+            .await
+            .unwrap();
+        object_store
+            .rename(
+                &ObjPath::from(parquet_files[2].path.as_str()),
+                &QuarantineFilePath::new(&parquet_files[2].path),
+            )
+            .await
+            .unwrap();
+
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let write_buffer = WriteBufferImpl::new(
+            Arc::clone(&persister),
+            None::<Arc<WalImpl>>,
+            time_provider,
+            SegmentDuration::new_5m(),
+            crate::test_help::make_exec(),
+            None,
+        )
+        .await
+        .unwrap();
+        let paths = |write_buffer: &WriteBufferImpl<WalImpl, MockProvider>| {
+            write_buffer
+                .parquet_files("foo", "cpu")
+                .into_iter()
+                .map(|file| file.path)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths(&write_buffer),
+            vec![parquet_files[0].path.clone(), parquet_files[1].path.clone()]
+        );
+
+        verify_parquet_files(
+            persister,
+            Arc::clone(&write_buffer.segment_state),
+            parquet_files[..2].to_vec(),
+        )
+        .await;
+        assert_eq!(paths(&write_buffer), vec![parquet_files[0].path.clone()]);
+    }
+
+    #[tokio::test]
+    async fn quarantines_rejected_lines() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let write_buffer = WriteBufferImpl::new(
+            Arc::clone(&persister),
+            None::<Arc<WalImpl>>,
+            Arc::clone(&time_provider),
+            SegmentDuration::new_5m(),
+            crate::test_help::make_exec(),
+            None,
+        )
+        .await
+        .unwrap();
+        let db_name = NamespaceName::new("foo").unwrap();
+
+        write_buffer
+            .write_lp(
+                db_name.clone(),
+                "cpu bar=1 10",
+                Time::from_timestamp_nanos(100),
+                false,
+                Precision::Nanosecond,
+                None,
+            )
+            .await
+            .unwrap();
+        write_buffer
+            .catalog()
+            .set_quarantine_rejected_writes("foo", true)
+            .unwrap();
+
+        let summary = write_buffer
+            .write_lp(
+                db_name.clone(),
+                "cpu bar=2 20\ncpu bar=\"two\" 30\ncpu bar=t 40",
+                Time::from_timestamp_nanos(200),
+                true,
+                Precision::Nanosecond,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(summary.invalid_lines.len(), 2);
+
+        // a write rejected as a whole has all of its rejected lines quarantined too
+        write_buffer
+            .write_lp(
+                db_name,
+                "cpu bar=\"three\" 50\ncpu bar=3 55\ncpu bar=f 60",
+                Time::from_timestamp_nanos(300),
+                false,
+                Precision::Nanosecond,
+                None,
+            )
+            .await
+            .unwrap_err();
+
+        let actual = write_buffer.get_table_record_batches("foo", REJECTED_WRITES_TABLE_NAME);
+        let actual = arrow::util::pretty::pretty_format_batches(&actual)
+            .unwrap()
+            .to_string();
+        assert!(actual.contains(r#"| cpu bar="two" 30 "#), "{actual}");
+        // lines are quarantined as they were written
+        assert!(actual.contains("| cpu bar=t 40 "), "{actual}");
+        assert!(actual.contains(r#"| cpu bar="three" 50 "#), "{actual}");
+        assert!(actual.contains("| cpu bar=f 60 "), "{actual}");
+        assert_eq!(actual.matches("expected type").count(), 4, "{actual}");
+
+        // the valid line was still written
+        let expected = [
+            "+-----+--------------------------------+",
+            "| bar | time                           |",
+            "+-----+--------------------------------+",
+            "| 1.0 | 1970-01-01T00:00:00.000000010Z |",
+            "| 2.0 | 1970-01-01T00:00:00.000000020Z |",
+            "+-----+--------------------------------+",
+        ];
         let actual = write_buffer.get_table_record_batches("foo", "cpu");
         assert_batches_eq!(&expected, &actual);
     }
 
+    #[tokio::test]
+    async fn quarantined_lines_are_exempt_from_time_validation() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let write_buffer = WriteBufferImpl::new(
+            Arc::clone(&persister),
+            None::<Arc<WalImpl>>,
+            Arc::clone(&time_provider),
+            SegmentDuration::new_5m(),
+            crate::test_help::make_exec(),
+            None,
+        )
+        .await
+        .unwrap();
+        let catalog = write_buffer.catalog();
+        catalog.set_quarantine_rejected_writes("foo", true).unwrap();
+        // the rejected writes table is written with nanosecond timestamps
+        catalog
+            .set_time_validation(
+                "foo",
+                Some(TimeValidation {
+                    precision: Some(Precision::Second),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+
+        let summary = write_buffer
+            .write_lp(
+                NamespaceName::new("foo").unwrap(),
+                "cpu bar=1 1\ncpu bar=2 2",
+                Time::from_timestamp(5, 0).unwrap(),
+                true,
+                Precision::Nanosecond,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(summary.invalid_lines.len(), 2);
+
+        let actual = write_buffer.get_table_record_batches("foo", REJECTED_WRITES_TABLE_NAME);
+        let actual = arrow::util::pretty::pretty_format_batches(&actual)
+            .unwrap()
+            .to_string();
+        assert!(actual.contains("| cpu bar=1 1 "), "{actual}");
+        assert!(actual.contains("| cpu bar=2 2 "), "{actual}");
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn returns_chunks_across_buffered_persisted_and_persisting_data() {
         let dir = test_helpers::tmp_dir().unwrap().into_path();
@@ -981,6 +2336,7 @@ mod tests {
             Arc::clone(&time_provider),
             segment_duration,
             crate::test_help::make_exec(),
+            None,
         )
         .await
         .unwrap();
@@ -995,6 +2351,7 @@ mod tests {
                 Time::from_timestamp_nanos(123),
                 false,
                 Precision::Nanosecond,
+                None,
             )
             .await
             .unwrap();
@@ -1037,6 +2394,7 @@ mod tests {
                 Time::from_timestamp(900, 0).unwrap(),
                 false,
                 Precision::Nanosecond,
+                None,
             )
             .await
             .unwrap();
@@ -1058,6 +2416,7 @@ mod tests {
             Arc::clone(&time_provider),
             segment_duration,
             crate::test_help::make_exec(),
+            None,
         )
         .await
         .unwrap();
@@ -1072,6 +2431,7 @@ mod tests {
                 Time::from_timestamp(950, 0).unwrap(),
                 false,
                 Precision::Nanosecond,
+                None,
             )
             .await
             .unwrap();
@@ -1102,6 +2462,7 @@ mod tests {
             Arc::clone(&time_provider),
             segment_duration,
             crate::test_help::make_exec(),
+            None,
         )
         .await
         .unwrap();
@@ -1120,6 +2481,7 @@ mod tests {
                 new_segment_time,
                 false,
                 Precision::Nanosecond,
+                None,
             )
             .await
             .unwrap();
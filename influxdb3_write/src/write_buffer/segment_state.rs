@@ -1,28 +1,34 @@
 //! State for the write buffer segments.
 
-use crate::catalog::{Catalog, DatabaseSchema};
+use crate::catalog::{Catalog, DatabaseSchema, TIME_COLUMN_NAME};
 use crate::chunk::BufferChunk;
+use crate::paths::ParquetFilePath;
 use crate::wal::WalSegmentWriterNoopImpl;
-use crate::write_buffer::buffer_segment::{ClosedBufferSegment, OpenBufferSegment, WriteBatch};
+use crate::write_buffer::buffer_segment::{
+    merge_late_data_segment, ClosedBufferSegment, OpenBufferSegment, WriteBatch,
+};
 use crate::{
     persister, wal, write_buffer, ParquetFile, PersistedSegment, Persister, SegmentDuration,
     SegmentId, SegmentRange, SequenceNumber, Wal, WalOp,
 };
 use arrow::datatypes::SchemaRef;
-#[cfg(test)]
 use arrow::record_batch::RecordBatch;
-use data_types::{ChunkId, ChunkOrder, TableId, TransitionPartitionId};
-use datafusion::common::DataFusionError;
+use data_types::{ChunkId, ChunkOrder, TableId, TimestampMinMax, TransitionPartitionId};
+use datafusion::common::stats::Precision;
+use datafusion::common::{DataFusionError, Statistics};
 use datafusion::execution::context::SessionState;
-use datafusion::logical_expr::Expr;
+use datafusion::logical_expr::{BinaryExpr, Expr, Operator};
+use datafusion::scalar::ScalarValue;
 use iox_query::chunk_statistics::create_chunk_statistics;
 use iox_query::QueryChunk;
 use iox_time::{Time, TimeProvider};
-use observability_deps::tracing::error;
+use observability_deps::tracing::{debug, error};
 use parking_lot::RwLock;
+use schema::sort::SortKey;
 #[cfg(test)]
 use schema::Schema;
 use std::collections::BTreeMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::watch;
@@ -41,8 +47,13 @@ pub(crate) struct SegmentState<T, W> {
     // Map of segment start times to open segments. Should always have a segment open for the
     // start time that time.now falls into.
     segments: BTreeMap<Time, OpenBufferSegment>,
-    persisting_segments: BTreeMap<Time, Arc<ClosedBufferSegment>>,
-    persisted_segments: BTreeMap<Time, Arc<PersistedSegment>>,
+    // Persisting and persisted segments are keyed by id rather than start time as late arriving
+    // data can open a new segment for a time range that has already been persisted.
+    persisting_segments: BTreeMap<SegmentId, Arc<ClosedBufferSegment>>,
+    persisted_segments: BTreeMap<SegmentId, Arc<PersistedSegment>>,
+    // Paths of persisted parquet files that failed checksum verification and were moved to
+    // quarantine. These are left out of query results.
+    quarantined_parquet_files: HashSet<String>,
 }
 
 impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
@@ -57,34 +68,80 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
         persisted_segments: Vec<PersistedSegment>,
         wal: Option<Arc<W>>,
     ) -> Self {
-        let mut segments = BTreeMap::new();
-        for segment in open_segments {
-            segments.insert(segment.segment_range().start_time, segment);
-        }
-
         let mut persisting_segments_map = BTreeMap::new();
         for segment in persisting_segments {
-            persisting_segments_map.insert(segment.segment_range.start_time, Arc::new(segment));
+            persisting_segments_map.insert(segment.segment_id, Arc::new(segment));
         }
 
         let mut persisted_segments_map = BTreeMap::new();
         for segment in persisted_segments {
-            persisted_segments_map.insert(
-                Time::from_timestamp_nanos(segment.segment_min_time),
-                Arc::new(segment),
-            );
+            persisted_segments_map.insert(segment.segment_id, Arc::new(segment));
         }
 
-        Self {
+        let mut state = Self {
             segment_duration,
             last_segment_id,
             catalog,
             time_provider,
             wal,
-            segments,
+            segments: BTreeMap::new(),
             persisting_segments: persisting_segments_map,
             persisted_segments: persisted_segments_map,
+            quarantined_parquet_files: HashSet::new(),
+        };
+
+        for mut segment in open_segments {
+            let start_time = segment.segment_range().start_time;
+            if state.has_persisted_data_for(start_time) {
+                segment.mark_late_data();
+            }
+            state.segments.insert(start_time, segment);
         }
+
+        state
+    }
+
+    /// Returns true if a segment for the range starting at `start_time` has already been closed
+    /// for persistence, meaning that any newly opened segment for it holds late arriving data.
+    fn has_persisted_data_for(&self, start_time: Time) -> bool {
+        self.persisting_segments
+            .values()
+            .any(|segment| segment.segment_range.start_time == start_time)
+            || self
+                .persisted_segments
+                .values()
+                .any(|segment| self.persisted_segment_start_time(segment) == start_time)
+    }
+
+    /// Returns the start time of the segment range that a persisted segment holds data for.
+    fn persisted_segment_start_time(&self, segment: &PersistedSegment) -> Time {
+        SegmentRange::from_time_and_duration(
+            Time::from_timestamp_nanos(segment.segment_min_time),
+            self.segment_duration,
+            false,
+        )
+        .start_time
+    }
+
+    /// Returns a persisted segment of late arriving data, along with the segment persisted
+    /// earlier for the same time range if it is still around.
+    fn late_data_segment_to_merge(
+        &self,
+    ) -> Option<(Arc<PersistedSegment>, Option<Arc<PersistedSegment>>)> {
+        let late_segment = self
+            .persisted_segments
+            .values()
+            .find(|segment| segment.late_data)?;
+        let start_time = self.persisted_segment_start_time(late_segment);
+        let persisted_segment = self
+            .persisted_segments
+            .values()
+            .find(|segment| {
+                !segment.late_data && self.persisted_segment_start_time(segment) == start_time
+            })
+            .cloned();
+
+        Some((Arc::clone(late_segment), persisted_segment))
     }
 
     pub(crate) fn write_ops_to_segment(
@@ -131,25 +188,30 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
             .map_err(|e| DataFusionError::Execution(format!("schema error {}", e)))?;
 
         let mut chunks: Vec<Arc<dyn QueryChunk>> = vec![];
+        let time_range = filter_time_range(filters);
 
         for segment in self.segments.values() {
-            if let Some(batch) = segment.table_record_batch(
-                &db_schema.name,
-                table_name,
-                Arc::clone(&arrow_schema),
-                filters,
-            ) {
-                let batch = batch.map_err(|e| {
-                    DataFusionError::Execution(format!("error getting batches {}", e))
-                })?;
+            if let Some(table_buffer) = segment.table_buffer(&db_schema.name, table_name) {
+                // skip buffers whose data can't match the query before copying out any rows
+                if !time_range.overlaps(table_buffer.timestamp_min_max()) {
+                    continue;
+                }
+                let batch = table_buffer
+                    .record_batch(Arc::clone(&arrow_schema), filters)
+                    .map_err(|e| {
+                        DataFusionError::Execution(format!("error getting batches {}", e))
+                    })?;
                 let row_count = batch.num_rows();
 
-                let chunk_stats = create_chunk_statistics(
+                // use the time range of the data actually buffered rather than the segment range
+                // so the planner can tell which chunks don't overlap
+                let mut chunk_stats = create_chunk_statistics(
                     Some(row_count),
                     &schema,
-                    Some(segment.segment_range().timestamp_min_max()),
+                    Some(table_buffer.timestamp_min_max()),
                     None,
                 );
+                add_batch_null_counts(&mut chunk_stats, &batch);
 
                 chunks.push(Arc::new(BufferChunk {
                     batches: vec![batch],
@@ -160,6 +222,7 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
                         segment.segment_key(),
                     ),
                     sort_key: None,
+                    may_contain_pk_duplicates: table_buffer.may_contain_pk_duplicates(),
                     id: ChunkId::new(),
                     chunk_order: ChunkOrder::new(
                         chunks
@@ -172,23 +235,27 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
         }
 
         for persisting_segment in self.persisting_segments.values() {
-            if let Some(batch) = persisting_segment.buffered_data.table_record_batches(
-                &db_schema.name,
-                table_name,
-                Arc::clone(&arrow_schema),
-                filters,
-            ) {
-                let batch = batch.map_err(|e| {
-                    DataFusionError::Execution(format!("error getting batches {}", e))
-                })?;
+            if let Some(table_buffer) = persisting_segment
+                .buffered_data
+                .table_buffer(&db_schema.name, table_name)
+            {
+                if !time_range.overlaps(table_buffer.timestamp_min_max()) {
+                    continue;
+                }
+                let batch = table_buffer
+                    .record_batch(Arc::clone(&arrow_schema), filters)
+                    .map_err(|e| {
+                        DataFusionError::Execution(format!("error getting batches {}", e))
+                    })?;
                 let row_count = batch.num_rows();
 
-                let chunk_stats = create_chunk_statistics(
+                let mut chunk_stats = create_chunk_statistics(
                     Some(row_count),
                     &schema,
-                    Some(persisting_segment.segment_range.timestamp_min_max()),
+                    Some(table_buffer.timestamp_min_max()),
                     None,
                 );
+                add_batch_null_counts(&mut chunk_stats, &batch);
 
                 chunks.push(Arc::new(BufferChunk {
                     batches: vec![batch],
@@ -199,6 +266,7 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
                         &persisting_segment.segment_key,
                     ),
                     sort_key: None,
+                    may_contain_pk_duplicates: table_buffer.may_contain_pk_duplicates(),
                     id: ChunkId::new(),
                     chunk_order: ChunkOrder::new(
                         chunks
@@ -213,17 +281,33 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
         Ok(chunks)
     }
 
+    /// Excludes a persisted parquet file that failed checksum verification from queries.
+    pub(crate) fn quarantine_parquet_file(&mut self, path: impl Into<String>) {
+        self.quarantined_parquet_files.insert(path.into());
+    }
+
+    /// Returns the persisted parquet files for the table along with the sort key they were
+    /// written with. Segments persisted before sort keys were recorded have no sort key.
+    /// Quarantined files are not returned.
     pub(crate) fn get_parquet_files(
         &self,
         database_name: &str,
         table_name: &str,
-    ) -> Vec<ParquetFile> {
+    ) -> Vec<(ParquetFile, Option<SortKey>)> {
         let mut parquet_files = vec![];
 
         for segment in self.persisted_segments.values() {
             segment.databases.get(database_name).map(|db| {
                 db.tables.get(table_name).map(|table| {
-                    parquet_files.extend(table.parquet_files.clone());
+                    let sort_key = (!table.sort_key.is_empty())
+                        .then(|| SortKey::from_columns(table.sort_key.iter().map(String::as_str)));
+                    parquet_files.extend(
+                        table
+                            .parquet_files
+                            .iter()
+                            .filter(|file| !self.quarantined_parquet_files.contains(&file.path))
+                            .map(|file| (file.clone(), sort_key.clone())),
+                    );
                 })
             });
         }
@@ -231,6 +315,69 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
         parquet_files
     }
 
+    /// Returns the persisted segments holding parquet files whose data is all past the retention
+    /// period of their database, with those files removed, along with the paths of the removed
+    /// files.
+    fn segments_without_expired_parquet_files(
+        &self,
+        current_time: Time,
+    ) -> Vec<(PersistedSegment, Vec<String>)> {
+        let cutoffs: HashMap<String, i64> = self
+            .catalog
+            .retention_periods()
+            .into_iter()
+            .filter_map(|(db_name, retention)| {
+                let cutoff = current_time.checked_sub(retention)?;
+                Some((db_name, cutoff.timestamp_nanos()))
+            })
+            .collect();
+        if cutoffs.is_empty() {
+            return vec![];
+        }
+        let expired = |db_name: &str, parquet_file: &ParquetFile| {
+            cutoffs
+                .get(db_name)
+                .is_some_and(|cutoff| parquet_file.max_time < *cutoff)
+        };
+
+        self.persisted_segments
+            .values()
+            .filter(|segment| {
+                segment.databases.iter().any(|(db_name, db)| {
+                    db.tables
+                        .values()
+                        .flat_map(|table| &table.parquet_files)
+                        .any(|file| expired(db_name, file))
+                })
+            })
+            .map(|segment| {
+                let mut segment = segment.as_ref().clone();
+                let mut expired_paths = vec![];
+                for (db_name, db) in &mut segment.databases {
+                    for table in db.tables.values_mut() {
+                        table.parquet_files.retain(|file| {
+                            if !expired(db_name, file) {
+                                return true;
+                            }
+                            segment.segment_row_count -= file.row_count;
+                            segment.segment_parquet_size_bytes -= file.size_bytes;
+                            expired_paths.push(file.path.clone());
+                            false
+                        });
+                    }
+                    db.tables.retain(|_, table| !table.parquet_files.is_empty());
+                }
+                segment.databases.retain(|_, db| !db.tables.is_empty());
+                (segment, expired_paths)
+            })
+            .collect()
+    }
+
+    /// The number of closed segments waiting to be persisted
+    pub(crate) fn persisting_segment_count(&self) -> usize {
+        self.persisting_segments.len()
+    }
+
     #[cfg(test)]
     pub(crate) fn persisted_segments(&self) -> Vec<Arc<PersistedSegment>> {
         self.persisted_segments.values().cloned().collect()
@@ -286,7 +433,7 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
             let closed_segment = Arc::new(segment.into_closed_segment(Arc::clone(&self.catalog)));
 
             self.persisting_segments
-                .insert(segment_start, Arc::clone(&closed_segment));
+                .insert(closed_segment.segment_id, Arc::clone(&closed_segment));
 
             closed_segment
         })
@@ -313,7 +460,7 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
                 None => Box::new(WalSegmentWriterNoopImpl::new(segment_id)),
             };
 
-            let segment = OpenBufferSegment::new(
+            let mut segment = OpenBufferSegment::new(
                 Arc::clone(&self.catalog),
                 segment_id,
                 segment_range,
@@ -322,6 +469,9 @@ impl<T: TimeProvider, W: Wal> SegmentState<T, W> {
                 segment_writer,
                 None,
             );
+            if self.has_persisted_data_for(time) {
+                segment.mark_late_data();
+            }
             self.segments.insert(time, segment);
         }
 
@@ -427,9 +577,136 @@ where
         }
     }
 
+    merge_late_data_segments(Arc::clone(&persister), Arc::clone(&segment_state), executor).await?;
+
+    delete_expired_parquet_files(persister, segment_state, current_time).await
+}
+
+// Deletes the persisted parquet files whose data is all past the retention period of their
+// database. The segments are persisted without the files before they're deleted, so a failure
+// part way through leaves files behind rather than segments referring to missing files.
+async fn delete_expired_parquet_files<P, T, W>(
+    persister: Arc<P>,
+    segment_state: Arc<RwLock<SegmentState<T, W>>>,
+    current_time: Time,
+) -> Result<(), crate::Error>
+where
+    P: Persister,
+    persister::Error: From<<P as Persister>::Error>,
+    T: TimeProvider,
+    W: Wal,
+    write_buffer::Error: From<<P as Persister>::Error>,
+{
+    let segments = segment_state
+        .read()
+        .segments_without_expired_parquet_files(current_time);
+
+    for (segment, expired_paths) in segments {
+        persister
+            .persist_segment(&segment)
+            .await
+            .map_err(persister::Error::from)?;
+        debug!(
+            segment_id = segment.segment_id.0,
+            count = expired_paths.len(),
+            "deleting parquet files past their retention period"
+        );
+        segment_state
+            .write()
+            .persisted_segments
+            .insert(segment.segment_id, Arc::new(segment));
+
+        for path in expired_paths {
+            persister
+                .delete_parquet_file(ParquetFilePath::from(path.as_str()))
+                .await
+                .map_err(persister::Error::from)?;
+        }
+    }
+
     Ok(())
 }
 
+// Merges every persisted segment of late arriving data into the segment persisted earlier for
+// the same time range, so that each time range is left with a single persisted segment. A late
+// segment whose earlier segment is gone takes its place instead.
+async fn merge_late_data_segments<P, T, W>(
+    persister: Arc<P>,
+    segment_state: Arc<RwLock<SegmentState<T, W>>>,
+    executor: Arc<iox_query::exec::Executor>,
+) -> Result<(), crate::Error>
+where
+    P: Persister,
+    persister::Error: From<<P as Persister>::Error>,
+    T: TimeProvider,
+    W: Wal,
+    write_buffer::Error: From<<P as Persister>::Error>,
+{
+    loop {
+        // merged one at a time, as a merge changes the segment the next late one merges into
+        let (merge, catalog, segment_duration) = {
+            let segment_state = segment_state.read();
+            (
+                segment_state.late_data_segment_to_merge(),
+                Arc::clone(&segment_state.catalog),
+                segment_state.segment_duration,
+            )
+        };
+        let Some((late_segment, persisted_segment)) = merge else {
+            return Ok(());
+        };
+
+        let (merged_segment, replaced_files) = match persisted_segment {
+            Some(persisted_segment) => {
+                merge_late_data_segment(
+                    &persisted_segment,
+                    &late_segment,
+                    Arc::clone(&persister),
+                    Arc::clone(&executor),
+                    &catalog,
+                    segment_duration,
+                )
+                .await?
+            }
+            None => {
+                let segment = PersistedSegment {
+                    late_data: false,
+                    ..late_segment.as_ref().clone()
+                };
+                persister
+                    .persist_segment(&segment)
+                    .await
+                    .map_err(persister::Error::from)?;
+                (segment, vec![])
+            }
+        };
+
+        if merged_segment.segment_id != late_segment.segment_id {
+            persister
+                .delete_segment(late_segment.segment_id)
+                .await
+                .map_err(persister::Error::from)?;
+        }
+
+        {
+            let mut segment_state = segment_state.write();
+            segment_state
+                .persisted_segments
+                .remove(&late_segment.segment_id);
+            segment_state
+                .persisted_segments
+                .insert(merged_segment.segment_id, Arc::new(merged_segment));
+        }
+
+        for path in replaced_files {
+            persister
+                .delete_parquet_file(ParquetFilePath::from(path.as_str()))
+                .await
+                .map_err(persister::Error::from)?;
+        }
+    }
+}
+
 // Performs the following:
 // 1. persist the segment to the object store
 // 2. remove the segment from the persisting_segments map and add it to the persisted_segments map
@@ -448,18 +725,15 @@ where
     W: Wal,
     write_buffer::Error: From<<P as Persister>::Error>,
 {
-    let closed_segment_start_time = closed_segment.segment_range.start_time;
     let closed_segment_id = closed_segment.segment_id;
     let persisted_segment = closed_segment.persist(persister, executor, None).await?;
 
     {
         let mut segment_state = segment_state.write();
-        segment_state
-            .persisting_segments
-            .remove(&closed_segment_start_time);
+        segment_state.persisting_segments.remove(&closed_segment_id);
         segment_state
             .persisted_segments
-            .insert(closed_segment_start_time, Arc::new(persisted_segment));
+            .insert(closed_segment_id, Arc::new(persisted_segment));
     }
 
     if let Some(wal) = wal {
@@ -469,16 +743,77 @@ where
     Ok(())
 }
 
+/// Sets the null count of each column in the statistics to the nulls in the batch, so that the
+/// planner can use them in its cost estimates.
+fn add_batch_null_counts(stats: &mut Statistics, batch: &RecordBatch) {
+    for (column_stats, column) in stats.column_statistics.iter_mut().zip(batch.columns()) {
+        column_stats.null_count = Precision::Exact(column.null_count());
+    }
+}
+
+/// Returns the inclusive time range that the filters restrict the `time` column to. Only simple
+/// comparisons of `time` against a timestamp literal are considered; anything else leaves the
+/// range unbounded.
+pub(crate) fn filter_time_range(filters: &[Expr]) -> TimestampMinMax {
+    let mut range = TimestampMinMax {
+        min: i64::MIN,
+        max: i64::MAX,
+    };
+
+    for expr in filters {
+        let Expr::BinaryExpr(BinaryExpr { left, op, right }) = expr else {
+            continue;
+        };
+        let (op, value) = match (left.as_ref(), right.as_ref()) {
+            (Expr::Column(c), Expr::Literal(v)) if c.name == TIME_COLUMN_NAME => (*op, v),
+            (Expr::Literal(v), Expr::Column(c)) if c.name == TIME_COLUMN_NAME => match op.swap() {
+                Some(op) => (op, v),
+                None => continue,
+            },
+            _ => continue,
+        };
+        let ScalarValue::TimestampNanosecond(Some(value), _) = value else {
+            continue;
+        };
+        let value = *value;
+
+        match op {
+            Operator::Eq => {
+                range.min = range.min.max(value);
+                range.max = range.max.min(value);
+            }
+            Operator::Gt => range.min = range.min.max(value.saturating_add(1)),
+            Operator::GtEq => range.min = range.min.max(value),
+            Operator::Lt => range.max = range.max.min(value.saturating_sub(1)),
+            Operator::LtEq => range.max = range.max.min(value),
+            _ => {}
+        }
+    }
+
+    range
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::paths::SegmentInfoFilePath;
+    use crate::persister::PersisterImpl;
     use crate::test_helpers::lp_to_write_batch;
     use crate::wal::WalImpl;
     use crate::{SegmentFile, WalSegmentReader, WalSegmentWriter};
+    use arrow_util::assert_batches_sorted_eq;
+    use datafusion::prelude::{col, lit_timestamp_nano};
+    use futures_util::TryStreamExt;
+    use iox_query::exec::IOxSessionContext;
     use iox_time::MockProvider;
+    use object_store::memory::InMemory;
+    use object_store::path::Path as ObjPath;
+    use object_store::ObjectStore;
     use parking_lot::Mutex;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
     use std::any::Any;
     use std::fmt::Debug;
+    use std::num::NonZeroU64;
     use write_buffer::buffer_segment::tests::TestPersister;
 
     #[test]
@@ -656,6 +991,366 @@ mod tests {
         assert_eq!(deleted_segments, vec![SegmentId::new(1), SegmentId::new(2)]);
     }
 
+    #[test]
+    fn get_table_chunks_prunes_buffers_outside_time_filter() {
+        let catalog = Arc::new(Catalog::new());
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let segment_duration = SegmentDuration::new_5m();
+
+        let mut open_segment1 = OpenBufferSegment::new(
+            Arc::clone(&catalog),
+            SegmentId::new(1),
+            SegmentRange::from_time_and_duration(
+                Time::from_timestamp_nanos(0),
+                segment_duration,
+                false,
+            ),
+            time_provider.now(),
+            catalog.sequence_number(),
+            Box::new(WalSegmentWriterNoopImpl::new(SegmentId::new(1))),
+            None,
+        );
+        open_segment1
+            .buffer_writes(lp_to_write_batch(&catalog, "foo", "cpu bar=1 10"))
+            .unwrap();
+
+        let mut open_segment2 = OpenBufferSegment::new(
+            Arc::clone(&catalog),
+            SegmentId::new(2),
+            SegmentRange::from_time_and_duration(
+                Time::from_timestamp(300, 0).unwrap(),
+                segment_duration,
+                false,
+            ),
+            time_provider.now(),
+            catalog.sequence_number(),
+            Box::new(WalSegmentWriterNoopImpl::new(SegmentId::new(2))),
+            None,
+        );
+        open_segment2
+            .buffer_writes(lp_to_write_batch(&catalog, "foo", "cpu bar=2 300000000000"))
+            .unwrap();
+
+        let segment_state: SegmentState<MockProvider, TestWal> = SegmentState::new(
+            segment_duration,
+            SegmentId::new(3),
+            Arc::clone(&catalog),
+            Arc::clone(&time_provider),
+            vec![open_segment1, open_segment2],
+            vec![],
+            vec![],
+            None,
+        );
+        let db_schema = catalog.db_schema("foo").unwrap();
+        let ctx = IOxSessionContext::with_testing();
+        let state = ctx.inner().state();
+
+        let chunks = segment_state
+            .get_table_chunks(Arc::clone(&db_schema), "cpu", &[], None, &state)
+            .unwrap();
+        assert_eq!(chunks.len(), 2);
+
+        let filter = col("time").gt_eq(lit_timestamp_nano(300_000_000_000_i64));
+        let chunks = segment_state
+            .get_table_chunks(Arc::clone(&db_schema), "cpu", &[filter], None, &state)
+            .unwrap();
+        assert_eq!(chunks.len(), 1);
+
+        let filter = col("time").gt(lit_timestamp_nano(300_000_000_000_i64));
+        let chunks = segment_state
+            .get_table_chunks(db_schema, "cpu", &[filter], None, &state)
+            .unwrap();
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn buffer_chunks_record_null_counts() {
+        let catalog = Arc::new(Catalog::new());
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let segment_duration = SegmentDuration::new_5m();
+
+        let mut open_segment = OpenBufferSegment::new(
+            Arc::clone(&catalog),
+            SegmentId::new(1),
+            SegmentRange::from_time_and_duration(
+                Time::from_timestamp_nanos(0),
+                segment_duration,
+                false,
+            ),
+            time_provider.now(),
+            catalog.sequence_number(),
+            Box::new(WalSegmentWriterNoopImpl::new(SegmentId::new(1))),
+            None,
+        );
+        open_segment
+            .buffer_writes(lp_to_write_batch(
+                &catalog,
+                "foo",
+                "cpu bar=1 10\ncpu,host=a bar=2 20\ncpu baz=3 30",
+            ))
+            .unwrap();
+
+        let segment_state: SegmentState<MockProvider, TestWal> = SegmentState::new(
+            segment_duration,
+            SegmentId::new(2),
+            Arc::clone(&catalog),
+            Arc::clone(&time_provider),
+            vec![open_segment],
+            vec![],
+            vec![],
+            None,
+        );
+        let db_schema = catalog.db_schema("foo").unwrap();
+        let ctx = IOxSessionContext::with_testing();
+
+        let chunks = segment_state
+            .get_table_chunks(db_schema, "cpu", &[], None, &ctx.inner().state())
+            .unwrap();
+        assert_eq!(chunks.len(), 1);
+
+        let stats = chunks[0].stats();
+        let null_counts = chunks[0]
+            .schema()
+            .as_arrow()
+            .fields()
+            .iter()
+            .zip(&stats.column_statistics)
+            .map(|(field, column_stats)| (field.name().clone(), column_stats.null_count.clone()))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            null_counts,
+            BTreeMap::from([
+                ("bar".to_string(), Precision::Exact(1)),
+                ("baz".to_string(), Precision::Exact(2)),
+                ("host".to_string(), Precision::Exact(2)),
+                ("time".to_string(), Precision::Exact(0)),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn late_data_is_merged_into_earlier_segment() {
+        let catalog = Arc::new(Catalog::new());
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let segment_duration = SegmentDuration::new_5m();
+
+        let mut open_segment = OpenBufferSegment::new(
+            Arc::clone(&catalog),
+            SegmentId::new(1),
+            SegmentRange::from_time_and_duration(
+                Time::from_timestamp_nanos(0),
+                segment_duration,
+                false,
+            ),
+            time_provider.now(),
+            catalog.sequence_number(),
+            Box::new(WalSegmentWriterNoopImpl::new(SegmentId::new(1))),
+            None,
+        );
+        open_segment
+            .buffer_writes(lp_to_write_batch(
+                &catalog,
+                "foo",
+                "cpu,host=a bar=1 10\ncpu,host=b bar=1 10",
+            ))
+            .unwrap();
+
+        let segment_state: SegmentState<MockProvider, TestWal> = SegmentState::new(
+            segment_duration,
+            SegmentId::new(1),
+            Arc::clone(&catalog),
+            Arc::clone(&time_provider),
+            vec![open_segment],
+            vec![],
+            vec![],
+            None,
+        );
+        let segment_state = Arc::new(RwLock::new(segment_state));
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+
+        time_provider.set(Time::from_timestamp(900, 0).unwrap());
+        persist_and_cleanup_ready_segments(
+            Arc::clone(&persister),
+            Arc::clone(&segment_state),
+            Arc::clone(&time_provider),
+            None,
+            crate::test_help::make_exec(),
+        )
+        .await
+        .unwrap();
+
+        // a write for the already persisted time range opens a new segment flagged as late
+        {
+            let mut state = segment_state.write();
+            state
+                .write_batch_to_segment(
+                    Time::from_timestamp_nanos(0),
+                    lp_to_write_batch(&catalog, "foo", "cpu,host=a bar=2 10"),
+                    catalog.sequence_number(),
+                )
+                .unwrap();
+            assert!(state
+                .segment_for_time(Time::from_timestamp_nanos(0))
+                .unwrap()
+                .is_late_data());
+        }
+
+        time_provider.set(Time::from_timestamp(1800, 0).unwrap());
+        persist_and_cleanup_ready_segments(
+            Arc::clone(&persister),
+            Arc::clone(&segment_state),
+            Arc::clone(&time_provider),
+            None,
+            crate::test_help::make_exec(),
+        )
+        .await
+        .unwrap();
+
+        // the late segment is merged into the one persisted earlier for the same range, its
+        // rows replacing those with the same tags and time
+        let parquet_files = {
+            let state = segment_state.read();
+            let persisted = state.persisted_segments();
+            assert_eq!(persisted.len(), 1);
+            assert_eq!(persisted[0].segment_id, SegmentId::new(1));
+            assert!(!persisted[0].late_data);
+            assert_eq!(persisted[0].segment_row_count, 2);
+            state.get_parquet_files("foo", "cpu")
+        };
+        assert_eq!(parquet_files.len(), 1);
+
+        let bytes = persister
+            .load_parquet_file(ParquetFilePath::from(parquet_files[0].0.path.as_str()))
+            .await
+            .unwrap();
+        let batches = ParquetRecordBatchReaderBuilder::try_new(bytes)
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+-----+------+--------------------------------+",
+                "| bar | host | time                           |",
+                "+-----+------+--------------------------------+",
+                "| 1.0 | b    | 1970-01-01T00:00:00.000000010Z |",
+                "| 2.0 | a    | 1970-01-01T00:00:00.000000010Z |",
+                "+-----+------+--------------------------------+",
+            ],
+            &batches
+        );
+
+        // only the merged parquet file and segment are left in object storage
+        let parquet_paths = object_store
+            .list(Some(&ObjPath::from("dbs")))
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(parquet_paths, [parquet_files[0].0.path.clone()]);
+        let segment_paths = object_store
+            .list(Some(&SegmentInfoFilePath::dir()))
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            segment_paths,
+            [SegmentInfoFilePath::new(SegmentId::new(1)).to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn deletes_parquet_files_past_retention_period() {
+        let catalog = Arc::new(Catalog::new());
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let segment_duration = SegmentDuration::new_5m();
+
+        let mut open_segment = OpenBufferSegment::new(
+            Arc::clone(&catalog),
+            SegmentId::new(1),
+            SegmentRange::from_time_and_duration(
+                Time::from_timestamp_nanos(0),
+                segment_duration,
+                false,
+            ),
+            time_provider.now(),
+            catalog.sequence_number(),
+            Box::new(WalSegmentWriterNoopImpl::new(SegmentId::new(1))),
+            None,
+        );
+        open_segment
+            .buffer_writes(lp_to_write_batch(&catalog, "foo", "cpu,host=a bar=1 10"))
+            .unwrap();
+        catalog
+            .set_retention_period("foo", NonZeroU64::new(3_600_000))
+            .unwrap();
+
+        let segment_state: SegmentState<MockProvider, TestWal> = SegmentState::new(
+            segment_duration,
+            SegmentId::new(1),
+            Arc::clone(&catalog),
+            Arc::clone(&time_provider),
+            vec![open_segment],
+            vec![],
+            vec![],
+            None,
+        );
+        let segment_state = Arc::new(RwLock::new(segment_state));
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let parquet_paths = |object_store: Arc<dyn ObjectStore>| async move {
+            object_store
+                .list(Some(&ObjPath::from("dbs")))
+                .map_ok(|meta| meta.location.to_string())
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap()
+        };
+
+        // the file is persisted within the retention period, so it's kept
+        time_provider.set(Time::from_timestamp(900, 0).unwrap());
+        persist_and_cleanup_ready_segments(
+            Arc::clone(&persister),
+            Arc::clone(&segment_state),
+            Arc::clone(&time_provider),
+            None,
+            crate::test_help::make_exec(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            segment_state.read().get_parquet_files("foo", "cpu").len(),
+            1
+        );
+        assert_eq!(parquet_paths(Arc::clone(&object_store)).await.len(), 1);
+
+        // until all of its data is past it
+        time_provider.set(Time::from_timestamp(3_601, 0).unwrap());
+        persist_and_cleanup_ready_segments(
+            Arc::clone(&persister),
+            Arc::clone(&segment_state),
+            Arc::clone(&time_provider),
+            None,
+            crate::test_help::make_exec(),
+        )
+        .await
+        .unwrap();
+        assert!(segment_state
+            .read()
+            .get_parquet_files("foo", "cpu")
+            .is_empty());
+        assert!(parquet_paths(Arc::clone(&object_store)).await.is_empty());
+
+        // and the persisted segment no longer refers to it
+        let persisted = persister.load_segments(10).await.unwrap();
+        assert_eq!(persisted.len(), 1);
+        assert!(persisted[0].databases.is_empty());
+        assert_eq!(persisted[0].segment_row_count, 0);
+    }
+
     #[derive(Debug, Default)]
     struct TestWal {
         deleted_wal_segments: Mutex<Vec<SegmentId>>,
@@ -11,7 +11,9 @@ use arrow::record_batch::RecordBatch;
 use data_types::{PartitionKey, TimestampMinMax};
 use datafusion::logical_expr::{BinaryExpr, Expr};
 use observability_deps::tracing::debug;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::mem::size_of;
 use std::sync::Arc;
 use thiserror::Error;
@@ -34,6 +36,10 @@ pub struct TableBuffer {
     pub(crate) data: BTreeMap<String, Builder>,
     row_count: usize,
     index: BufferIndex,
+    /// The max time seen for each series (keyed by a hash of its tag set), used to prove that
+    /// the buffer can't contain primary key duplicates. Set to `None` once a row arrives for a
+    /// series at or before its max time, at which point the buffer must be deduplicated.
+    series_max_time: Option<HashMap<u64, i64>>,
 }
 
 impl TableBuffer {
@@ -45,6 +51,7 @@ impl TableBuffer {
             data: Default::default(),
             row_count: 0,
             index: BufferIndex::new(index_columns),
+            series_max_time: Some(HashMap::new()),
         }
     }
 
@@ -52,6 +59,8 @@ impl TableBuffer {
         let new_row_count = rows.len();
 
         for (row_index, r) in rows.into_iter().enumerate() {
+            self.track_series_time(&r);
+
             let mut value_added = HashSet::with_capacity(r.fields.len());
 
             for f in r.fields {
@@ -197,6 +206,42 @@ impl TableBuffer {
         self.row_count += new_row_count;
     }
 
+    /// Returns false if every row in the buffer arrived after all previous rows of the same
+    /// series, which means no two rows can share a primary key and queries can skip the
+    /// dedup sort for this buffer.
+    pub fn may_contain_pk_duplicates(&self) -> bool {
+        self.series_max_time.is_none()
+    }
+
+    fn track_series_time(&mut self, row: &Row) {
+        let Some(series_max_time) = self.series_max_time.as_mut() else {
+            return;
+        };
+
+        // combine the tag hashes so that tag order in the line protocol doesn't matter
+        let series_key = row
+            .fields
+            .iter()
+            .filter_map(|f| match &f.value {
+                FieldData::Tag(v) => {
+                    let mut hasher = DefaultHasher::new();
+                    f.name.hash(&mut hasher);
+                    v.hash(&mut hasher);
+                    Some(hasher.finish())
+                }
+                _ => None,
+            })
+            .fold(0u64, |acc, h| acc.wrapping_add(h));
+
+        match series_max_time.get_mut(&series_key) {
+            Some(max_time) if *max_time >= row.time => self.series_max_time = None,
+            Some(max_time) => *max_time = row.time,
+            None => {
+                series_max_time.insert(series_key, row.time);
+            }
+        }
+    }
+
     pub fn timestamp_min_max(&self) -> TimestampMinMax {
         TimestampMinMax {
             min: self.timestamp_min,
@@ -240,6 +285,9 @@ impl TableBuffer {
             size += k.len() + size_of::<String>() + v._size();
         }
         size += self.index._size();
+        if let Some(series_max_time) = &self.series_max_time {
+            size += series_max_time.len() * (size_of::<u64>() + size_of::<i64>());
+        }
         size
     }
 }
@@ -626,6 +674,42 @@ mod tests {
         table_buffer.add_rows(rows);
 
         let size = table_buffer._computed_size();
-        assert_eq!(size, 18126);
+        assert_eq!(size, 18222);
+    }
+
+    #[test]
+    fn tracks_possible_pk_duplicates() {
+        fn row(tags: &[(&str, &str)], time: i64) -> Row {
+            let mut fields: Vec<Field> = tags
+                .iter()
+                .map(|(k, v)| Field {
+                    name: k.to_string(),
+                    value: FieldData::Tag(v.to_string()),
+                })
+                .collect();
+            fields.push(Field {
+                name: "time".to_string(),
+                value: FieldData::Timestamp(time),
+            });
+            Row { time, fields }
+        }
+
+        let mut table_buffer = TableBuffer::new(PartitionKey::from("table"), &[]);
+
+        // different series can share a timestamp, and tag order doesn't change the series
+        table_buffer.add_rows(vec![
+            row(&[("host", "a"), ("region", "us")], 1),
+            row(&[("host", "b"), ("region", "us")], 1),
+            row(&[("region", "us"), ("host", "a")], 2),
+        ]);
+        assert!(!table_buffer.may_contain_pk_duplicates());
+
+        // a row at or before the last time of its series may be a duplicate
+        table_buffer.add_rows(vec![row(&[("host", "b"), ("region", "us")], 1)]);
+        assert!(table_buffer.may_contain_pk_duplicates());
+
+        // once set it stays set
+        table_buffer.add_rows(vec![row(&[("host", "c")], 10)]);
+        assert!(table_buffer.may_contain_pk_duplicates());
     }
 }